@@ -0,0 +1,110 @@
+// Copyright (C) 2018 Sebastian Dröge <sebastian@centricular.com>
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+//! Compares the throughput and latency of the ts-appsrc queue backends.
+//!
+//! Usage: ts-appsrc-queue-benchmark [n-buffers] [max-buffers]
+
+use gst::prelude::*;
+
+use std::env;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn run(backend: &str, n_buffers: u64, max_buffers: u32) {
+    let pipeline = gst::Pipeline::default();
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", format!("queue-benchmark-{backend}"))
+        .property("max-buffers", max_buffers)
+        .property_from_str("queue-backend", backend)
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .property("async", false)
+        .property("signal-handoffs", true)
+        .build()
+        .unwrap();
+    pipeline.add_many([&appsrc, &sink]).unwrap();
+    appsrc.link(&sink).unwrap();
+
+    // Push instant of each buffer, indexed by the buffer offset
+    let pushed = Arc::new(Mutex::new(Vec::with_capacity(n_buffers as usize)));
+    let latencies = Arc::new(Mutex::new(Vec::with_capacity(n_buffers as usize)));
+    let (done_tx, done_rx) = mpsc::sync_channel(1);
+    sink.connect("handoff", false, {
+        let pushed = pushed.clone();
+        let latencies = latencies.clone();
+        move |args| {
+            let now = Instant::now();
+            let buffer = args[1].get::<gst::Buffer>().unwrap();
+            let offset = buffer.offset() as usize;
+            let push = pushed.lock().unwrap()[offset];
+            latencies.lock().unwrap().push(now.duration_since(push));
+            if offset as u64 + 1 == n_buffers {
+                let _ = done_tx.send(());
+            }
+            None
+        }
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let start = Instant::now();
+    for offset in 0..n_buffers {
+        let mut buffer = gst::Buffer::new();
+        buffer.get_mut().unwrap().set_offset(offset);
+        pushed.lock().unwrap().push(Instant::now());
+        // Retry while the queue is full
+        while !appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]) {
+            thread::yield_now();
+            *pushed.lock().unwrap().last_mut().unwrap() = Instant::now();
+        }
+    }
+    done_rx.recv_timeout(Duration::from_secs(60)).unwrap();
+    let elapsed = start.elapsed();
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let mut latencies = latencies.lock().unwrap();
+    latencies.sort_unstable();
+    let percentile = |p: usize| latencies[(latencies.len() * p / 100).min(latencies.len() - 1)];
+
+    println!(
+        "{backend:>8}: {:>10.0} buffers/s, latency median {:?}, p99 {:?}, max {:?}",
+        n_buffers as f64 / elapsed.as_secs_f64(),
+        percentile(50),
+        percentile(99),
+        latencies.last().unwrap(),
+    );
+}
+
+fn main() {
+    gst::init().unwrap();
+    gstthreadshare::plugin_register_static().unwrap();
+
+    let args = env::args().collect::<Vec<_>>();
+    let n_buffers: u64 = args.get(1).map_or(100_000, |arg| arg.parse().unwrap());
+    let max_buffers: u32 = args.get(2).map_or(1000, |arg| arg.parse().unwrap());
+
+    for backend in ["channel", "ring"] {
+        run(backend, n_buffers, max_buffers);
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2026 agent <agent@local>
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+#[cfg(feature = "v1_20")]
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+#[cfg(feature = "v1_20")]
+use flate2::Compression;
+
+use std::io;
+#[cfg(feature = "v1_20")]
+use std::io::Write;
+
+/// Name of the custom meta marking a buffer with compressed memory.
+///
+/// The `size` field holds the size of the uncompressed data.
+#[cfg(feature = "v1_20")]
+pub const META_NAME: &str = "GstTsAppSrcCompressedMeta";
+
+/// Replaces the memory of `buffer` with its compressed data.
+///
+/// The timestamps, flags and metas are kept. `buffer` is returned unchanged
+/// if its data doesn't compress.
+#[cfg(feature = "v1_20")]
+pub fn compress(mut buffer: gst::Buffer) -> io::Result<gst::Buffer> {
+    let (size, data) = {
+        let map = buffer
+            .map_readable()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+        encoder.write_all(map.as_slice())?;
+
+        (map.size(), encoder.finish()?)
+    };
+
+    if data.len() >= size {
+        return Ok(buffer);
+    }
+
+    let buffer_mut = buffer.make_mut();
+    buffer_mut.replace_all_memory(gst::Memory::from_mut_slice(data));
+    let mut meta = gst::meta::CustomMeta::add(buffer_mut, META_NAME)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    meta.mut_structure().set("size", size as u64);
+
+    Ok(buffer)
+}
+
+/// Restores the data of a buffer compressed by `compress`.
+///
+/// Other buffers are returned unchanged.
+#[cfg(feature = "v1_20")]
+pub fn decompress(mut buffer: gst::Buffer) -> io::Result<gst::Buffer> {
+    let Ok(meta) = gst::meta::CustomMeta::from_buffer(&buffer, META_NAME) else {
+        return Ok(buffer);
+    };
+    let size = meta.structure().get::<u64>("size").unwrap_or(0) as usize;
+
+    let data = {
+        let map = buffer
+            .map_readable()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let mut decoder = DeflateDecoder::new(Vec::with_capacity(size));
+        decoder.write_all(map.as_slice())?;
+        decoder.finish()?
+    };
+
+    let buffer_mut = buffer.make_mut();
+    buffer_mut.replace_all_memory(gst::Memory::from_mut_slice(data));
+    if let Ok(meta) = gst::meta::CustomMeta::from_mut_buffer(buffer_mut, META_NAME) {
+        let _ = meta.remove();
+    }
+
+    Ok(buffer)
+}
+
+/// Compressed buffers can't be marked without custom metas.
+#[cfg(not(feature = "v1_20"))]
+pub fn compress(buffer: gst::Buffer) -> io::Result<gst::Buffer> {
+    Ok(buffer)
+}
+
+#[cfg(not(feature = "v1_20"))]
+pub fn decompress(buffer: gst::Buffer) -> io::Result<gst::Buffer> {
+    Ok(buffer)
+}
@@ -18,9 +18,9 @@
 //
 // SPDX-License-Identifier: LGPL-2.1-or-later
 
-use futures::channel::mpsc;
-use futures::future::BoxFuture;
+use futures::future::{self, BoxFuture};
 use futures::prelude::*;
+use futures::stream::BoxStream;
 
 use gst::glib;
 use gst::prelude::*;
@@ -28,18 +28,99 @@ use gst::subclass::prelude::*;
 
 use once_cell::sync::Lazy;
 
-use std::sync::Mutex;
-use std::time::Duration;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::u32;
 
 use crate::runtime::prelude::*;
-use crate::runtime::{Context, PadSrc, Task, TaskState};
+use crate::runtime::{task, timer, Context, PadSrc, Task, TaskState};
+
+use super::compress;
+use super::queue::{self, ItemReceiver, ItemSender};
+use super::reorder::Reorder;
+use super::spill::{Spill, Unspilled};
+use super::{
+    CapsIntersectMode, ClockSource, DoTimestampMode, ErrorCode, FlushMode, NotLinkedPolicy,
+    QueueBackend, RetryErrors, StreamType,
+};
 
 const DEFAULT_CONTEXT: &str = "";
 const DEFAULT_CONTEXT_WAIT: Duration = Duration::ZERO;
 const DEFAULT_CAPS: Option<gst::Caps> = None;
 const DEFAULT_MAX_BUFFERS: u32 = 10;
 const DEFAULT_DO_TIMESTAMP: bool = false;
+const DEFAULT_FORMAT: gst::Format = gst::Format::Time;
+const DEFAULT_CAPTURE_LATENCY: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_POST_STREAM_MESSAGES: bool = false;
+const DEFAULT_FLUSH_MODE: FlushMode = FlushMode::Cancel;
+const DEFAULT_REFERENCE_TIMESTAMP_CAPS: Option<gst::Caps> = None;
+const DEFAULT_REFERENCE_CLOCK: Option<gst::Clock> = None;
+const DEFAULT_CLOCK_SOURCE: ClockSource = ClockSource::Pipeline;
+const DEFAULT_DO_TIMESTAMP_MODE: DoTimestampMode = DoTimestampMode::Always;
+const DEFAULT_NOT_LINKED_POLICY: NotLinkedPolicy = NotLinkedPolicy::Pause;
+const DEFAULT_SEND_FLUSH_ON_STOP: bool = false;
+const DEFAULT_CAPS_INTERSECT_MODE: CapsIntersectMode = CapsIntersectMode::First;
+const DEFAULT_PERSIST_SEGMENT: bool = false;
+const DEFAULT_MERGE_MEMORY: bool = false;
+const DEFAULT_MAX_BUFFER_SIZE: u64 = 0;
+const DEFAULT_EMIT_SIGNALS: bool = false;
+const DEFAULT_SPILL_TO_DISK: bool = false;
+const DEFAULT_SPILL_PATH: Option<PathBuf> = None;
+const DEFAULT_MAX_RETRIES: u32 = 0;
+const DEFAULT_RETRY_INTERVAL: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+const DEFAULT_RETRY_ERRORS: RetryErrors = RetryErrors::ERROR;
+const DEFAULT_AUTO_DISCONT: bool = true;
+const DEFAULT_NUM_BUFFERS: i32 = -1;
+const DEFAULT_WARN_ON_FULL: bool = false;
+const DEFAULT_FILL_ON_UNDERRUN: bool = false;
+const DEFAULT_DO_STATS: bool = false;
+const DEFAULT_REORDER_WINDOW: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_IS_LIVE: bool = true;
+const DEFAULT_CLIP_TO_SEGMENT: bool = false;
+const DEFAULT_SPLIT_SIZE: u32 = 0;
+const DEFAULT_EOS_DEBOUNCE: Duration = Duration::ZERO;
+const DEFAULT_LOCK_CAPS: bool = false;
+const DEFAULT_QUEUE_BACKEND: QueueBackend = QueueBackend::Channel;
+const DEFAULT_DEFAULT_CAPS: Option<gst::Caps> = None;
+const DEFAULT_REJECT_WHEN_PAUSED: bool = false;
+const DEFAULT_STREAM_TYPE: StreamType = StreamType::Stream;
+const DEFAULT_COMPRESS_QUEUE: bool = false;
+const DEFAULT_LOCATION: Option<PathBuf> = None;
+const DEFAULT_BLOCKSIZE: u32 = 4096;
+const DEFAULT_DROP_EMPTY_BUFFERS: bool = false;
+const DEFAULT_STREAM_FLAGS: gst::StreamFlags = gst::StreamFlags::empty();
+const DEFAULT_MAX_BITRATE: u64 = 0;
+const DEFAULT_STRICT_ZERO_COPY: bool = false;
+const DEFAULT_USE_BUFFERING: bool = false;
+const DEFAULT_LOW_WATERMARK: f64 = 0.01;
+const DEFAULT_HIGH_WATERMARK: f64 = 0.99;
+const DEFAULT_MIN_FILL: u32 = 0;
+const DEFAULT_MIN_FILL_PERCENT: u32 = 0;
+const DEFAULT_MAX_BUFFER_AGE: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_SPARSE: bool = false;
+const DEFAULT_PASSIVE: bool = false;
+const DEFAULT_STREAM_ID_SEED: u64 = 0;
+const DEFAULT_FILLER_BUFFER: Option<gst::Buffer> = None;
+
+// Minimum interval between two queue full warning messages
+const FULL_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+
+// Maximum duration the `negotiate` action signal waits for the task
+const NEGOTIATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Interval between two checks of the task state while waiting for negotiation
+const NEGOTIATE_STATE_CHECK_INTERVAL: Duration = Duration::from_millis(20);
 
 #[derive(Debug, Clone)]
 struct Settings {
@@ -48,6 +129,58 @@ struct Settings {
     caps: Option<gst::Caps>,
     max_buffers: u32,
     do_timestamp: bool,
+    format: gst::Format,
+    capture_latency: gst::ClockTime,
+    post_stream_messages: bool,
+    flush_mode: FlushMode,
+    reference_timestamp_caps: Option<gst::Caps>,
+    reference_clock: Option<gst::Clock>,
+    clock_source: ClockSource,
+    do_timestamp_mode: DoTimestampMode,
+    not_linked_policy: NotLinkedPolicy,
+    send_flush_on_stop: bool,
+    caps_intersect_mode: CapsIntersectMode,
+    persist_segment: bool,
+    merge_memory: bool,
+    max_buffer_size: u64,
+    emit_signals: bool,
+    spill_to_disk: bool,
+    spill_path: Option<PathBuf>,
+    max_retries: u32,
+    retry_interval: gst::ClockTime,
+    retry_errors: RetryErrors,
+    auto_discont: bool,
+    num_buffers: i32,
+    warn_on_full: bool,
+    fill_on_underrun: bool,
+    filler_buffer: Option<gst::Buffer>,
+    do_stats: bool,
+    reorder_window: gst::ClockTime,
+    is_live: bool,
+    clip_to_segment: bool,
+    split_size: u32,
+    eos_debounce: Duration,
+    lock_caps: bool,
+    queue_backend: QueueBackend,
+    default_caps: Option<gst::Caps>,
+    reject_when_paused: bool,
+    stream_type: StreamType,
+    compress_queue: bool,
+    location: Option<PathBuf>,
+    blocksize: u32,
+    drop_empty_buffers: bool,
+    stream_flags: gst::StreamFlags,
+    max_bitrate: u64,
+    strict_zero_copy: bool,
+    use_buffering: bool,
+    low_watermark: f64,
+    high_watermark: f64,
+    min_fill: u32,
+    min_fill_percent: u32,
+    max_buffer_age: gst::ClockTime,
+    sparse: bool,
+    passive: bool,
+    stream_id_seed: u64,
 }
 
 impl Default for Settings {
@@ -58,6 +191,58 @@ impl Default for Settings {
             caps: DEFAULT_CAPS,
             max_buffers: DEFAULT_MAX_BUFFERS,
             do_timestamp: DEFAULT_DO_TIMESTAMP,
+            format: DEFAULT_FORMAT,
+            capture_latency: DEFAULT_CAPTURE_LATENCY,
+            post_stream_messages: DEFAULT_POST_STREAM_MESSAGES,
+            flush_mode: DEFAULT_FLUSH_MODE,
+            reference_timestamp_caps: DEFAULT_REFERENCE_TIMESTAMP_CAPS,
+            reference_clock: DEFAULT_REFERENCE_CLOCK,
+            clock_source: DEFAULT_CLOCK_SOURCE,
+            do_timestamp_mode: DEFAULT_DO_TIMESTAMP_MODE,
+            not_linked_policy: DEFAULT_NOT_LINKED_POLICY,
+            send_flush_on_stop: DEFAULT_SEND_FLUSH_ON_STOP,
+            caps_intersect_mode: DEFAULT_CAPS_INTERSECT_MODE,
+            persist_segment: DEFAULT_PERSIST_SEGMENT,
+            merge_memory: DEFAULT_MERGE_MEMORY,
+            max_buffer_size: DEFAULT_MAX_BUFFER_SIZE,
+            emit_signals: DEFAULT_EMIT_SIGNALS,
+            spill_to_disk: DEFAULT_SPILL_TO_DISK,
+            spill_path: DEFAULT_SPILL_PATH,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+            retry_errors: DEFAULT_RETRY_ERRORS,
+            auto_discont: DEFAULT_AUTO_DISCONT,
+            num_buffers: DEFAULT_NUM_BUFFERS,
+            warn_on_full: DEFAULT_WARN_ON_FULL,
+            fill_on_underrun: DEFAULT_FILL_ON_UNDERRUN,
+            filler_buffer: DEFAULT_FILLER_BUFFER,
+            do_stats: DEFAULT_DO_STATS,
+            reorder_window: DEFAULT_REORDER_WINDOW,
+            is_live: DEFAULT_IS_LIVE,
+            clip_to_segment: DEFAULT_CLIP_TO_SEGMENT,
+            split_size: DEFAULT_SPLIT_SIZE,
+            eos_debounce: DEFAULT_EOS_DEBOUNCE,
+            lock_caps: DEFAULT_LOCK_CAPS,
+            queue_backend: DEFAULT_QUEUE_BACKEND,
+            default_caps: DEFAULT_DEFAULT_CAPS,
+            reject_when_paused: DEFAULT_REJECT_WHEN_PAUSED,
+            stream_type: DEFAULT_STREAM_TYPE,
+            compress_queue: DEFAULT_COMPRESS_QUEUE,
+            location: DEFAULT_LOCATION,
+            blocksize: DEFAULT_BLOCKSIZE,
+            drop_empty_buffers: DEFAULT_DROP_EMPTY_BUFFERS,
+            stream_flags: DEFAULT_STREAM_FLAGS,
+            max_bitrate: DEFAULT_MAX_BITRATE,
+            strict_zero_copy: DEFAULT_STRICT_ZERO_COPY,
+            use_buffering: DEFAULT_USE_BUFFERING,
+            low_watermark: DEFAULT_LOW_WATERMARK,
+            high_watermark: DEFAULT_HIGH_WATERMARK,
+            min_fill: DEFAULT_MIN_FILL,
+            min_fill_percent: DEFAULT_MIN_FILL_PERCENT,
+            max_buffer_age: DEFAULT_MAX_BUFFER_AGE,
+            sparse: DEFAULT_SPARSE,
+            passive: DEFAULT_PASSIVE,
+            stream_id_seed: DEFAULT_STREAM_ID_SEED,
         }
     }
 }
@@ -70,10 +255,381 @@ static CAT: Lazy<gst::DebugCategory> = Lazy::new(|| {
     )
 });
 
+static MONOTONIC_CLOCK: Lazy<gst::Clock> = Lazy::new(|| {
+    glib::Object::builder::<gst::SystemClock>()
+        .property("clock-type", gst::ClockType::Monotonic)
+        .build()
+        .upcast()
+});
+
+static REALTIME_CLOCK: Lazy<gst::Clock> = Lazy::new(|| {
+    glib::Object::builder::<gst::SystemClock>()
+        .property("clock-type", gst::ClockType::Realtime)
+        .build()
+        .upcast()
+});
+
+// Number of the latest dwell times the dwell statistics are computed from
+const DWELL_WINDOW: usize = 1000;
+
+#[derive(Debug, Default)]
+struct Stats {
+    starved_iterations: u64,
+    ready_iterations: u64,
+    last_push: Option<Instant>,
+    interval_count: u64,
+    interval_min: Option<Duration>,
+    interval_max: Option<Duration>,
+    interval_sum: f64,
+    interval_sum_sq: f64,
+    dwell: VecDeque<Duration>,
+    deadline_drops: u64,
+    age_drops: u64,
+}
+
+impl Stats {
+    /// Accounts for the interval since the previous buffer push.
+    fn add_push(&mut self, now: Instant) {
+        if let Some(last_push) = self.last_push {
+            let interval = now.saturating_duration_since(last_push);
+            self.interval_count += 1;
+            self.interval_min = Some(self.interval_min.map_or(interval, |min| min.min(interval)));
+            self.interval_max = Some(self.interval_max.map_or(interval, |max| max.max(interval)));
+            let interval = interval.as_nanos() as f64;
+            self.interval_sum += interval;
+            self.interval_sum_sq += interval * interval;
+        }
+        self.last_push = Some(now);
+    }
+
+    /// Accounts for the time a buffer spent in the queue.
+    fn add_dwell(&mut self, dwell: Duration) {
+        if self.dwell.len() == DWELL_WINDOW {
+            self.dwell.pop_front();
+        }
+        self.dwell.push_back(dwell);
+    }
+
+    fn reset_intervals(&mut self) {
+        *self = Stats {
+            starved_iterations: self.starved_iterations,
+            ready_iterations: self.ready_iterations,
+            dwell: std::mem::take(&mut self.dwell),
+            deadline_drops: self.deadline_drops,
+            age_drops: self.age_drops,
+            ..Default::default()
+        };
+    }
+
+    fn to_structure(&self) -> gst::Structure {
+        let iterations = self.starved_iterations + self.ready_iterations;
+        let starvation_ratio = if iterations > 0 {
+            self.starved_iterations as f64 / iterations as f64
+        } else {
+            0.0
+        };
+
+        let (interval_mean, interval_stddev) = if self.interval_count > 0 {
+            let count = self.interval_count as f64;
+            let mean = self.interval_sum / count;
+            let variance = (self.interval_sum_sq / count - mean * mean).max(0.0);
+            (mean as u64, variance.sqrt() as u64)
+        } else {
+            (0, 0)
+        };
+
+        let mut dwell = self
+            .dwell
+            .iter()
+            .map(|dwell| dwell.as_nanos() as u64)
+            .collect::<Vec<_>>();
+        dwell.sort_unstable();
+        let percentile = |p: usize| {
+            dwell
+                .get((dwell.len() * p / 100).min(dwell.len().saturating_sub(1)))
+                .copied()
+                .unwrap_or(0)
+        };
+        let dwell_mean = if dwell.is_empty() {
+            0
+        } else {
+            dwell.iter().sum::<u64>() / dwell.len() as u64
+        };
+
+        gst::Structure::builder("application/x-ts-appsrc-stats")
+            .field("starved-iterations", self.starved_iterations)
+            .field("ready-iterations", self.ready_iterations)
+            .field("starvation-ratio", starvation_ratio)
+            .field("interval-count", self.interval_count)
+            .field(
+                "interval-min",
+                self.interval_min.map_or(0, |min| min.as_nanos() as u64),
+            )
+            .field(
+                "interval-max",
+                self.interval_max.map_or(0, |max| max.as_nanos() as u64),
+            )
+            .field("interval-mean", interval_mean)
+            .field("interval-stddev", interval_stddev)
+            .field("dwell-count", dwell.len() as u64)
+            .field("dwell-min", dwell.first().copied().unwrap_or(0))
+            .field("dwell-max", dwell.last().copied().unwrap_or(0))
+            .field("dwell-mean", dwell_mean)
+            .field("dwell-median", percentile(50))
+            .field("dwell-p99", percentile(99))
+            .field("deadline-drops", self.deadline_drops)
+            .field("age-drops", self.age_drops)
+            .build()
+    }
+}
+
+/// Arrival times of the queued buffers.
+#[derive(Debug, Default)]
+struct Arrivals {
+    queue: VecDeque<Instant>,
+    /// Incremented upon each flush, so that waiters can detect them.
+    flushes: u64,
+    /// Whether the application queued an EOS, so no more buffers are expected.
+    eos_queued: bool,
+    /// The number of buffers the queue can hold, set when preparing.
+    capacity: usize,
+    /// Woken up when a buffer or an EOS is queued, see `min-fill`.
+    fill_waker: Option<Waker>,
+}
+
+impl Arrivals {
+    fn wake_fill(&mut self) {
+        if let Some(waker) = self.fill_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Buffering state, see `use-buffering`.
+#[derive(Debug)]
+struct Buffering {
+    buffering: bool,
+    /// Percentage of the last buffering message, `None` if none was posted.
+    percent: Option<i32>,
+}
+
+impl Default for Buffering {
+    fn default() -> Self {
+        // Filling until the high watermark is first reached
+        Buffering {
+            buffering: true,
+            percent: None,
+        }
+    }
+}
+
+/// Items dropped because the queue was full, for the throttled warnings.
+#[derive(Debug, Default)]
+struct FullDrops {
+    last_warning: Option<Instant>,
+    dropped: u64,
+}
+
+/// Bursts allowed by the `max-bitrate` token bucket.
+const MAX_BITRATE_BURST: Duration = Duration::from_millis(100);
+
+/// Token bucket pacing the buffers to `max-bitrate`.
+#[derive(Debug, Default)]
+struct TokenBucket {
+    /// Available bits, negative when the last buffer exceeded them.
+    tokens: f64,
+    last_refill: Option<Instant>,
+}
+
+impl TokenBucket {
+    /// Consumes `bits` and returns how long to wait before pushing them.
+    fn consume(&mut self, bits: u64, max_bitrate: u64) -> Duration {
+        let now = Instant::now();
+        let rate = max_bitrate as f64;
+        let capacity = rate * MAX_BITRATE_BURST.as_secs_f64();
+        self.tokens = match self.last_refill {
+            Some(last_refill) => {
+                (self.tokens + now.duration_since(last_refill).as_secs_f64() * rate).min(capacity)
+            }
+            None => capacity,
+        };
+        self.last_refill = Some(now);
+
+        self.tokens -= bits as f64;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / rate)
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = TokenBucket::default();
+    }
+}
+
 #[derive(Debug)]
 enum StreamItem {
     Buffer(gst::Buffer),
+    CapsBuffer(gst::Caps, gst::Buffer),
+    /// Pushed by the task itself when the queue underruns.
+    Filler(gst::Buffer),
+    /// Pulled from the buffer stream, bypassing the queue.
+    Streamed(gst::Buffer),
+    /// Dropped if not forwarded before the running time deadline.
+    DeadlineBuffer(gst::ClockTime, gst::Buffer),
     Event(gst::Event),
+    Events(Vec<gst::Event>),
+    /// Held by the task until the stream reaches the running time.
+    ScheduledEvent(gst::ClockTime, gst::Event),
+    Negotiate(mpsc::SyncSender<bool>),
+    ResetStream,
+}
+
+impl StreamItem {
+    fn buffer(&self) -> Option<&gst::Buffer> {
+        match self {
+            StreamItem::Buffer(buffer)
+            | StreamItem::CapsBuffer(_, buffer)
+            | StreamItem::Filler(buffer)
+            | StreamItem::Streamed(buffer)
+            | StreamItem::DeadlineBuffer(_, buffer) => Some(buffer),
+            _ => None,
+        }
+    }
+
+    fn is_eos(&self) -> bool {
+        match self {
+            StreamItem::Event(event) => event.type_() == gst::EventType::Eos,
+            StreamItem::Events(events) => events
+                .last()
+                .is_some_and(|event| event.type_() == gst::EventType::Eos),
+            _ => false,
+        }
+    }
+}
+
+pub(super) type MakeBufferStream = dyn Fn() -> BoxStream<'static, gst::Buffer> + Send + Sync;
+
+/// Subscribes to the buffer stream set with `AppSrc::set_buffer_stream`.
+#[derive(Clone)]
+struct BufferStreamFactory(Arc<MakeBufferStream>);
+
+impl fmt::Debug for BufferStreamFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BufferStreamFactory")
+    }
+}
+
+struct BufferStream(BoxStream<'static, gst::Buffer>);
+
+impl fmt::Debug for BufferStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("BufferStream")
+    }
+}
+
+/// Reads the file at `location` in chunks of `blocksize` bytes, see the `location` property.
+///
+/// Reads are blocking, so they run on a dedicated thread instead of the Context.
+/// The chunks are queued like the pushed buffers, waiting for room in the queue.
+/// The file is read again from the start after going to READY, flushes don't
+/// rewind it.
+#[derive(Debug)]
+struct FileReader {
+    stopped: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl FileReader {
+    fn spawn(element: &super::AppSrc, location: PathBuf, blocksize: usize) -> io::Result<Self> {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = thread::Builder::new()
+            .name("ts-appsrc-file-reader".into())
+            .spawn({
+                let element = element.downgrade();
+                let stopped = stopped.clone();
+                move || read_file(element, location, blocksize, stopped)
+            })?;
+
+        Ok(FileReader { stopped, thread })
+    }
+
+    fn stop(self, appsrc: &AppSrc) {
+        {
+            // Set while holding the lock so that a reader waiting for room is woken up
+            let _arrivals = appsrc.arrivals.lock().unwrap();
+            self.stopped.store(true, Ordering::SeqCst);
+        }
+        appsrc.level_cond.notify_all();
+
+        let _ = self.thread.join();
+    }
+}
+
+fn read_file(
+    weak_element: glib::WeakRef<super::AppSrc>,
+    location: PathBuf,
+    blocksize: usize,
+    stopped: Arc<AtomicBool>,
+) {
+    let mut file = match File::open(&location) {
+        Ok(file) => file,
+        Err(err) => {
+            if let Some(element) = weak_element.upgrade() {
+                gst::element_error!(
+                    element,
+                    gst::ResourceError::OpenRead,
+                    ["Could not open file {}: {}", location.display(), err]
+                );
+            }
+            return;
+        }
+    };
+
+    let mut offset = 0;
+    loop {
+        let mut data = vec![0; blocksize];
+        let size = match file.read(&mut data) {
+            Ok(size) => size,
+            Err(err) => {
+                if let Some(element) = weak_element.upgrade() {
+                    gst::element_error!(
+                        element,
+                        gst::ResourceError::Read,
+                        ["Could not read file {}: {}", location.display(), err]
+                    );
+                }
+                return;
+            }
+        };
+
+        let Some(element) = weak_element.upgrade() else {
+            return;
+        };
+        let appsrc = element.imp();
+
+        if size == 0 {
+            gst::debug!(CAT, obj: element, "Reached the end of {}", location.display());
+            appsrc.queue_when_room(&stopped, "EOS", || appsrc.end_of_stream());
+            return;
+        }
+
+        data.truncate(size);
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_offset(offset);
+            buffer.set_offset_end(offset + size as u64);
+        }
+        offset += size as u64;
+
+        if !appsrc.queue_when_room(&stopped, "chunk", || {
+            appsrc.queue_buffer(buffer, None, true)
+        }) {
+            return;
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -82,6 +638,12 @@ struct AppSrcPadHandler;
 impl PadSrcHandler for AppSrcPadHandler {
     type ElementImpl = AppSrc;
 
+    /// Handles all the upstream events: `PadSrc` routes the events sent to the pad,
+    /// serialized or not, to this handler, there is no other path.
+    ///
+    /// Besides the events handled here, this includes QoS, navigation, step,
+    /// TOC select, select-streams and custom upstream events, which are
+    /// forwarded to the application via `upstream-event`.
     fn src_event(self, pad: &gst::Pad, imp: &AppSrc, event: gst::Event) -> bool {
         gst::log!(CAT, obj: pad, "Handling {:?}", event);
 
@@ -89,9 +651,43 @@ impl PadSrcHandler for AppSrcPadHandler {
         let ret = match event.view() {
             EventView::FlushStart(..) => imp.task.flush_start().await_maybe_on_context().is_ok(),
             EventView::FlushStop(..) => imp.task.flush_stop().await_maybe_on_context().is_ok(),
-            EventView::Reconfigure(..) => true,
-            EventView::Latency(..) => true,
-            _ => false,
+            EventView::Reconfigure(..) => {
+                // A newly linked peer gets the sticky events (stream-start, caps, segment)
+                // replayed by the pad before the next buffer, nothing to resend here.
+                imp.renegotiate();
+
+                // Resume a task paused due to the pad not being linked
+                if imp.task.state() == TaskState::Paused
+                    && imp.obj().current_state() == gst::State::Playing
+                    && !*imp.throttled.lock().unwrap()
+                {
+                    gst::debug!(CAT, obj: pad, "Resuming task");
+                    imp.task.start().await_maybe_on_context().is_ok()
+                } else {
+                    true
+                }
+            }
+            EventView::CustomUpstream(ev)
+                if ev
+                    .structure()
+                    .is_some_and(|s| s.name() == super::THROTTLE_EVENT_NAME) =>
+            {
+                let throttled = ev.structure().unwrap().get::<bool>("throttled");
+                match throttled {
+                    Ok(throttled) => imp.set_throttled(throttled),
+                    Err(err) => {
+                        gst::warning!(CAT, obj: pad, "Invalid throttle event: {}", err);
+                        false
+                    }
+                }
+            }
+            EventView::Seek(ev) => imp.seek(ev),
+            EventView::Latency(ev) => {
+                gst::debug!(CAT, obj: pad, "Configured latency {}", ev.latency());
+                *imp.configured_latency.lock().unwrap() = ev.latency();
+                true
+            }
+            _ => imp.unhandled_upstream_event(&event),
         };
 
         if ret {
@@ -109,18 +705,44 @@ impl PadSrcHandler for AppSrcPadHandler {
         use gst::QueryViewMut;
         let ret = match query.view_mut() {
             QueryViewMut::Latency(q) => {
-                q.set(true, gst::ClockTime::ZERO, gst::ClockTime::NONE);
+                let is_live = imp.settings.lock().unwrap().is_live;
+                q.set(is_live, imp.latency(), gst::ClockTime::NONE);
                 true
             }
+            QueryViewMut::Seeking(q) => {
+                let settings = imp.settings.lock().unwrap();
+                if q.format() == gst::Format::Bytes && settings.format == gst::Format::Bytes {
+                    let seekable = settings.stream_type == StreamType::RandomAccess;
+                    q.set(seekable, gst::format::Bytes::ZERO, gst::format::Bytes::NONE);
+                    true
+                } else {
+                    false
+                }
+            }
             QueryViewMut::Scheduling(q) => {
                 q.set(gst::SchedulingFlags::SEQUENTIAL, 1, -1, 0);
                 q.add_scheduling_modes(&[gst::PadMode::Push]);
                 true
             }
             QueryViewMut::Caps(q) => {
-                let caps = if let Some(caps) = imp.configured_caps.lock().unwrap().as_ref() {
+                let (mode, default_caps) = {
+                    let settings = imp.settings.lock().unwrap();
+                    (settings.caps_intersect_mode, settings.default_caps.clone())
+                };
+                // Advertise the default caps until caps are configured
+                let caps = imp.configured_caps.lock().unwrap().clone().or(default_caps);
+                let caps = if let Some(ref caps) = caps {
                     q.filter()
-                        .map(|f| f.intersect_with_mode(caps, gst::CapsIntersectMode::First))
+                        .map(|f| match mode {
+                            CapsIntersectMode::ZigZag => {
+                                f.intersect_with_mode(caps, gst::CapsIntersectMode::ZigZag)
+                            }
+                            CapsIntersectMode::First => {
+                                f.intersect_with_mode(caps, gst::CapsIntersectMode::First)
+                            }
+                            CapsIntersectMode::Subset if caps.is_subset(f) => caps.clone(),
+                            CapsIntersectMode::Subset => gst::Caps::new_empty(),
+                        })
                         .unwrap_or_else(|| caps.clone())
                 } else {
                     q.filter()
@@ -147,18 +769,66 @@ impl PadSrcHandler for AppSrcPadHandler {
 #[derive(Debug)]
 struct AppSrcTask {
     element: super::AppSrc,
-    receiver: mpsc::Receiver<StreamItem>,
+    /// Owned by the task: the flushes purge it from the task transitions, which
+    /// the `Task` serializes with the loop, so no lock is needed for teardown.
+    receiver: Box<dyn ItemReceiver<StreamItem>>,
     need_initial_events: bool,
     need_segment: bool,
+    stream_id: String,
+    stream_id_rng: StdRng,
+    segment: gst::Segment,
+    segment_done: bool,
+    last_running_time: Option<gst::ClockTime>,
+    task_state: TaskState,
+    need_discont: bool,
+    forwarded_buffers: u64,
+    last_position: Option<gst::ClockTime>,
+    reorder: Reorder<StreamItem>,
+    /// Items released by `reorder`, to be handled before the next items.
+    reordered: VecDeque<StreamItem>,
+    buffer_stream: Option<BufferStream>,
+    /// Whether to subscribe to the buffer stream before waiting for the next item.
+    need_buffer_stream: bool,
+    /// EOS held for `eos-debounce`, with its deadline.
+    pending_eos: Option<(Instant, StreamItem)>,
+    token_bucket: TokenBucket,
+    /// Events from `push-event-at`, sorted by running time.
+    scheduled_events: VecDeque<(gst::ClockTime, gst::Event)>,
+    /// Whether to wait for `min-fill` before pushing, i.e. on start and after a flush.
+    need_fill: bool,
 }
 
 impl AppSrcTask {
-    fn new(element: super::AppSrc, receiver: mpsc::Receiver<StreamItem>) -> Self {
+    fn new(element: super::AppSrc, receiver: Box<dyn ItemReceiver<StreamItem>>) -> Self {
+        // With a seed, e.g. in tests, each task generates the same sequence
+        // of stream ids for its stream starts
+        let stream_id_rng = match element.imp().settings.lock().unwrap().stream_id_seed {
+            0 => StdRng::from_entropy(),
+            seed => StdRng::seed_from_u64(seed),
+        };
+
         AppSrcTask {
             element,
             receiver,
             need_initial_events: true,
             need_segment: true,
+            stream_id: String::new(),
+            stream_id_rng,
+            segment: gst::Segment::new(),
+            segment_done: false,
+            last_running_time: None,
+            task_state: TaskState::Unprepared,
+            need_discont: true,
+            forwarded_buffers: 0,
+            last_position: None,
+            reorder: Reorder::default(),
+            reordered: VecDeque::new(),
+            buffer_stream: None,
+            need_buffer_stream: true,
+            pending_eos: None,
+            token_bucket: TokenBucket::default(),
+            scheduled_events: VecDeque::new(),
+            need_fill: true,
         }
     }
 }
@@ -166,333 +836,4229 @@ impl AppSrcTask {
 impl AppSrcTask {
     fn flush(&mut self) {
         // Purge the channel
-        while let Ok(Some(_item)) = self.receiver.try_next() {}
+        while let Ok(Some(_item)) = self.receiver.try_recv() {}
+        self.reorder.clear();
+        self.reordered.clear();
+        self.pending_eos = None;
+        {
+            let mut arrivals = self.element.imp().arrivals.lock().unwrap();
+            arrivals.queue.clear();
+            arrivals.flushes += 1;
+            arrivals.eos_queued = false;
+        }
+        self.element.imp().level_cond.notify_all();
+        // Filling up again, posted with the next queued buffer
+        *self.element.imp().buffering.lock().unwrap() = Buffering::default();
+
+        if let Some(spill) = self.element.imp().spill.lock().unwrap().as_mut() {
+            if let Err(err) = spill.clear() {
+                gst::warning!(CAT, obj: self.element, "Failed to clear spill file: {}", err);
+            }
+        }
     }
 
-    async fn push_item(&mut self, item: StreamItem) -> Result<gst::FlowSuccess, gst::FlowError> {
-        gst::log!(CAT, obj: self.element, "Handling {:?}", item);
-        let appsrc = self.element.imp();
+    async fn next_item(&mut self) -> Result<StreamItem, gst::FlowError> {
+        // Err means that the channel is empty, which counts as a starved iteration
+        let item = match self.receiver.try_recv() {
+            Ok(item) => {
+                self.element.imp().stats.lock().unwrap().ready_iterations += 1;
+                item
+            }
+            Err(_) => {
+                // Spilled items were queued after the items in the channel
+                if let Some(res) = self.unspill() {
+                    self.element.imp().stats.lock().unwrap().ready_iterations += 1;
+                    return res;
+                }
 
-        if self.need_initial_events {
-            gst::debug!(CAT, obj: self.element, "Pushing initial events");
+                self.element.imp().stats.lock().unwrap().starved_iterations += 1;
+                self.throttle().await;
 
-            let stream_id = format!("{:08x}{:08x}", rand::random::<u32>(), rand::random::<u32>());
-            let stream_start_evt = gst::event::StreamStart::builder(&stream_id)
-                .group_id(gst::GroupId::next())
-                .build();
-            appsrc.src_pad.push_event(stream_start_evt).await;
+                let filler = {
+                    let settings = self.element.imp().settings.lock().unwrap();
+                    settings
+                        .filler_buffer
+                        .clone()
+                        .filter(|_| settings.fill_on_underrun)
+                };
+                match filler.as_ref().and_then(|filler| filler.duration()) {
+                    Some(duration) => {
+                        // Keep the cadence if nothing is queued in time
+                        let item = {
+                            let delay = timer::delay_for(duration.into());
+                            let item = self.wait_item();
+                            futures::pin_mut!(item, delay);
+                            match future::select(item, delay).await {
+                                future::Either::Left((item, _)) => Some(item),
+                                future::Either::Right(_) => None,
+                            }
+                        };
+                        match item {
+                            Some(item) => item,
+                            None => return Ok(self.filler_item(filler.unwrap())),
+                        }
+                    }
+                    None => self.wait_item().await,
+                }
+            }
+        };
 
-            let caps = appsrc.settings.lock().unwrap().caps.clone();
-            if let Some(caps) = caps {
-                appsrc
-                    .src_pad
-                    .push_event(gst::event::Caps::new(&caps))
-                    .await;
-                *appsrc.configured_caps.lock().unwrap() = Some(caps.clone());
+        item.ok_or_else(|| {
+            if *self.element.imp().shutting_down.lock().unwrap() {
+                gst::debug!(CAT, obj: self.element, "Channel closed during shutdown");
+                gst::FlowError::Flushing
+            } else {
+                gst::element_warning!(
+                    &self.element,
+                    gst::CoreError::Failed,
+                    ["Internal channel sender dropped while streaming"]
+                );
+                self.element.imp().error_detailed(
+                    ErrorCode::ChannelClosed,
+                    "Internal channel sender dropped while streaming",
+                    None,
+                    None,
+                );
+                gst::FlowError::Error
             }
+        })
+    }
 
-            self.need_initial_events = false;
+    /// Waits for the next item from the channel or from the buffer stream if any.
+    ///
+    /// The end of the buffer stream is handled as an EOS.
+    async fn wait_item(&mut self) -> Option<StreamItem> {
+        if self.need_buffer_stream {
+            self.need_buffer_stream = false;
+            let appsrc = self.element.imp();
+            let factory = appsrc.buffer_stream.lock().unwrap().clone();
+            self.buffer_stream = match factory {
+                Some(factory) => {
+                    gst::debug!(CAT, obj: self.element, "Subscribing to the buffer stream");
+                    Some(BufferStream((factory.0)()))
+                }
+                None => None,
+            };
         }
 
-        if self.need_segment {
-            let segment_evt =
-                gst::event::Segment::new(&gst::FormattedSegment::<gst::format::Time>::new());
-            appsrc.src_pad.push_event(segment_evt).await;
+        let Some(stream) = self.buffer_stream.as_mut() else {
+            return self.receiver.next().await;
+        };
 
-            self.need_segment = false;
-        }
+        let streamed = match future::select(self.receiver.next(), stream.0.next()).await {
+            future::Either::Left((item, _)) => return item,
+            future::Either::Right((buffer, _)) => buffer,
+        };
 
-        match item {
-            StreamItem::Buffer(buffer) => {
-                gst::log!(CAT, obj: self.element, "Forwarding {:?}", buffer);
-                appsrc.src_pad.push(buffer).await
-            }
-            StreamItem::Event(event) => {
-                match event.view() {
-                    gst::EventView::Eos(_) => {
-                        // Let the caller push the event
-                        Err(gst::FlowError::Eos)
-                    }
-                    _ => {
-                        gst::log!(CAT, obj: self.element, "Forwarding {:?}", event);
-                        appsrc.src_pad.push_event(event).await;
-                        Ok(gst::FlowSuccess::Ok)
-                    }
-                }
+        Some(match streamed {
+            Some(buffer) => StreamItem::Streamed(buffer),
+            None => {
+                gst::debug!(CAT, obj: self.element, "Buffer stream ended");
+                self.buffer_stream = None;
+                StreamItem::Event(gst::event::Eos::new())
             }
+        })
+    }
+
+    /// Drops the buffer stream subscription, which is renewed when restarting.
+    fn drop_buffer_stream(&mut self) {
+        if self.buffer_stream.take().is_some() {
+            gst::debug!(CAT, obj: self.element, "Dropping the buffer stream subscription");
         }
+        self.need_buffer_stream = true;
     }
-}
 
-impl TaskImpl for AppSrcTask {
-    type Item = StreamItem;
+    /// Prepares a `filler` buffer following the last buffer pushed.
+    fn filler_item(&self, mut filler: gst::Buffer) -> StreamItem {
+        gst::log!(
+            CAT,
+            obj: self.element,
+            "Underrun, pushing filler at {}",
+            self.last_position.display(),
+        );
 
-    fn try_next(&mut self) -> BoxFuture<'_, Result<StreamItem, gst::FlowError>> {
-        async move {
-            self.receiver
-                .next()
-                .await
-                .ok_or_else(|| panic!("Internal channel sender dropped while Task is Started"))
+        {
+            let filler = filler.make_mut();
+            filler.set_pts(self.last_position);
+            filler.set_dts(gst::ClockTime::NONE);
+            filler.set_flags(gst::BufferFlags::GAP);
         }
-        .boxed()
+
+        StreamItem::Filler(filler)
     }
 
-    fn handle_item(&mut self, item: StreamItem) -> BoxFuture<'_, Result<(), gst::FlowError>> {
-        async move {
-            let res = self.push_item(item).await;
-            match res {
-                Ok(_) => {
-                    gst::log!(CAT, obj: self.element, "Successfully pushed item");
-                }
-                Err(gst::FlowError::Eos) => {
-                    gst::debug!(CAT, obj: self.element, "EOS");
-                    let appsrc = self.element.imp();
-                    appsrc.src_pad.push_event(gst::event::Eos::new()).await;
-                }
-                Err(gst::FlowError::Flushing) => {
-                    gst::debug!(CAT, obj: self.element, "Flushing");
-                }
-                Err(err) => {
-                    gst::error!(CAT, obj: self.element, "Got error {}", err);
-                    gst::element_error!(
-                        &self.element,
-                        gst::StreamError::Failed,
-                        ("Internal data stream error"),
-                        ["streaming stopped, reason {}", err]
-                    );
-                }
-            }
+    /// Retrieves the next spilled item if any.
+    fn unspill(&mut self) -> Option<Result<StreamItem, gst::FlowError>> {
+        let appsrc = self.element.imp();
+        let res = appsrc.spill.lock().unwrap().as_mut()?.pop()?;
 
-            res.map(drop)
-        }
-        .boxed()
+        Some(match res {
+            Ok(Unspilled::Buffer(None, buffer)) => Ok(StreamItem::Buffer(buffer)),
+            Ok(Unspilled::Buffer(Some(caps), buffer)) => Ok(StreamItem::CapsBuffer(caps, buffer)),
+            Ok(Unspilled::Item(item)) => Ok(item),
+            Err(err) => {
+                gst::element_error!(
+                    &self.element,
+                    gst::ResourceError::Read,
+                    ["Failed to read spill file: {}", err]
+                );
+                appsrc.error_detailed(
+                    ErrorCode::SpillFailed,
+                    &format!("Failed to read spill file: {err}"),
+                    None,
+                    None,
+                );
+                Err(gst::FlowError::Error)
+            }
+        })
     }
 
-    fn stop(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
-        async move {
-            gst::log!(CAT, obj: self.element, "Stopping task");
+    /// Keeps track of the task state, emitting `state-changed` if requested.
+    fn set_task_state(&mut self, task_state: TaskState) {
+        let old_task_state = std::mem::replace(&mut self.task_state, task_state);
+        if old_task_state == task_state {
+            return;
+        }
 
-            self.flush();
-            self.need_initial_events = true;
-            self.need_segment = true;
+        gst::debug!(
+            CAT,
+            obj: self.element,
+            "Task state {:?} -> {:?}",
+            old_task_state,
+            task_state
+        );
 
-            gst::log!(CAT, obj: self.element, "Task stopped");
-            Ok(())
+        if self.element.imp().settings.lock().unwrap().emit_signals {
+            self.element.emit_by_name::<()>(
+                "state-changed",
+                &[&format!("{old_task_state:?}"), &format!("{task_state:?}")],
+            );
         }
-        .boxed()
     }
 
-    fn flush_start(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
-        async move {
-            gst::log!(CAT, obj: self.element, "Starting task flush");
+    fn reset_stream(&mut self) {
+        gst::debug!(CAT, obj: self.element, "Resetting stream");
 
-            self.flush();
-            self.need_segment = true;
+        self.need_initial_events = true;
+        self.need_segment = true;
+        self.need_discont = true;
+        *self.element.imp().configured_caps.lock().unwrap() = None;
+    }
 
-            gst::log!(CAT, obj: self.element, "Task flush started");
-            Ok(())
+    fn post_stream_message(&self, name: &str) {
+        if !self
+            .element
+            .imp()
+            .settings
+            .lock()
+            .unwrap()
+            .post_stream_messages
+        {
+            return;
         }
-        .boxed()
+
+        let s = gst::Structure::builder(name)
+            .field("stream-id", self.stream_id.as_str())
+            .build();
+        let _ = self.element.post_message(
+            gst::message::Application::builder(s)
+                .src(&self.element)
+                .build(),
+        );
     }
-}
 
-#[derive(Debug)]
-pub struct AppSrc {
-    src_pad: PadSrc,
-    task: Task,
-    sender: Mutex<Option<mpsc::Sender<StreamItem>>>,
-    configured_caps: Mutex<Option<gst::Caps>>,
-    settings: Mutex<Settings>,
-}
+    async fn push_buffer(
+        &mut self,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut buffer = compress::decompress(buffer).map_err(|err| {
+            gst::error!(CAT, obj: self.element, "Failed to decompress buffer: {}", err);
+            gst::FlowError::Error
+        })?;
 
-impl AppSrc {
-    fn push_buffer(&self, mut buffer: gst::Buffer) -> bool {
-        let state = self.task.lock_state();
-        if *state != TaskState::Started && *state != TaskState::Paused {
-            gst::debug!(CAT, imp: self, "Rejecting buffer due to element state");
-            return false;
+        let (clip_to_segment, split_size) = {
+            let settings = self.element.imp().settings.lock().unwrap();
+            (settings.clip_to_segment, settings.split_size as usize)
+        };
+
+        if clip_to_segment {
+            match self.clip_to_segment(buffer) {
+                Some(clipped) => buffer = clipped,
+                None => return Ok(gst::FlowSuccess::Ok),
+            }
         }
 
-        let do_timestamp = self.settings.lock().unwrap().do_timestamp;
-        if do_timestamp {
-            let elem = self.obj();
-            if let Some(clock) = elem.clock() {
-                let base_time = elem.base_time();
-                let now = clock.time();
+        if split_size == 0 || buffer.size() <= split_size {
+            return self.push_chunk(buffer).await;
+        }
 
-                let buffer = buffer.make_mut();
-                buffer.set_dts(now.opt_checked_sub(base_time).ok().flatten());
-                buffer.set_pts(None);
-            } else {
-                gst::error!(CAT, imp: self, "Don't have a clock yet");
-                return false;
+        for chunk in self.split_buffer(&buffer, split_size)? {
+            self.push_chunk(chunk).await?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    /// Splits `buffer` into chunks of at most `split_size` bytes.
+    ///
+    /// The chunks share the memory of `buffer`, timestamps and durations
+    /// are interpolated from the offset of each chunk.
+    fn split_buffer(
+        &self,
+        buffer: &gst::Buffer,
+        split_size: usize,
+    ) -> Result<Vec<gst::Buffer>, gst::FlowError> {
+        let size = buffer.size();
+        gst::log!(
+            CAT,
+            obj: self.element,
+            "Splitting {:?} into chunks of {} bytes",
+            buffer,
+            split_size,
+        );
+
+        // Time elapsed at `offset` in the buffer
+        let elapsed = |offset: usize| {
+            buffer.duration().map(|duration| {
+                duration
+                    .mul_div_floor(offset as u64, size as u64)
+                    .unwrap_or(duration)
+            })
+        };
+
+        (0..size)
+            .step_by(split_size)
+            .map(|offset| {
+                let end = (offset + split_size).min(size);
+                let mut chunk = buffer
+                    .copy_region(
+                        gst::BufferCopyFlags::MEMORY
+                            | gst::BufferCopyFlags::FLAGS
+                            | gst::BufferCopyFlags::META,
+                        offset..end,
+                    )
+                    .map_err(|err| {
+                        gst::error!(CAT, obj: self.element, "Failed to split buffer: {}", err);
+                        gst::FlowError::Error
+                    })?;
+
+                let chunk_mut = chunk.make_mut();
+                let start_time = elapsed(offset);
+                chunk_mut.set_pts(buffer.pts().opt_add(start_time).or(buffer.pts()));
+                chunk_mut.set_dts(buffer.dts().opt_add(start_time).or(buffer.dts()));
+                chunk_mut.set_duration(elapsed(end).opt_sub(start_time));
+                if offset > 0 {
+                    chunk_mut.unset_flags(gst::BufferFlags::DISCONT | gst::BufferFlags::RESYNC);
+                }
+                if end < size {
+                    chunk_mut.unset_flags(gst::BufferFlags::MARKER);
+                }
+
+                Ok(chunk)
+            })
+            .collect()
+    }
+
+    async fn push_chunk(
+        &mut self,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        // With num-buffers=0, not even the first buffer is forwarded
+        let num_buffers = self.element.imp().settings.lock().unwrap().num_buffers;
+        if num_buffers >= 0 && self.forwarded_buffers >= num_buffers as u64 {
+            gst::debug!(CAT, obj: self.element, "Reached num-buffers {}", num_buffers);
+            return Err(gst::FlowError::Eos);
+        }
+
+        let n_memory = buffer.n_memory();
+        if n_memory > 1 && self.element.imp().settings.lock().unwrap().merge_memory {
+            gst::debug!(
+                CAT,
+                obj: self.element,
+                "Merging {} memories into a single one, copying {} bytes",
+                n_memory,
+                buffer.size(),
+            );
+            if let Some(memory) = buffer.all_memory() {
+                buffer.make_mut().replace_all_memory(memory);
             }
         }
 
-        let mut sender = self.sender.lock().unwrap();
-        match sender
-            .as_mut()
-            .unwrap()
-            .try_send(StreamItem::Buffer(buffer))
-        {
-            Ok(_) => true,
-            Err(err) => {
-                gst::error!(CAT, imp: self, "Failed to queue buffer: {}", err);
-                false
+        if self.need_discont {
+            if self.element.imp().settings.lock().unwrap().auto_discont {
+                if let Some(buffer) = self.buffer_mut(&mut buffer, "discont flag") {
+                    gst::debug!(CAT, obj: self.element, "Marking first buffer as discont");
+                    buffer.set_flags(gst::BufferFlags::DISCONT);
+                }
+            }
+            self.need_discont = false;
+        }
+
+        gst::log!(CAT, obj: self.element, "Forwarding {:?}", buffer);
+
+        let position = buffer
+            .pts()
+            .or_else(|| buffer.dts())
+            .map(|start| start.saturating_add(buffer.duration().unwrap_or(gst::ClockTime::ZERO)));
+
+        let appsrc = self.element.imp();
+
+        let max_bitrate = appsrc.settings.lock().unwrap().max_bitrate;
+        if max_bitrate > 0 {
+            let wait = self
+                .token_bucket
+                .consume(buffer.size() as u64 * 8, max_bitrate);
+            if !wait.is_zero() {
+                gst::log!(CAT, obj: self.element, "Pacing to max-bitrate, waiting {:?}", wait);
+                timer::delay_for(wait).await;
+            }
+        }
+
+        if appsrc.settings.lock().unwrap().sparse {
+            self.push_sparse_gap(&buffer).await;
+        }
+
+        let start_running_time = buffer.pts().or_else(|| buffer.dts()).and_then(|start| {
+            self.segment
+                .downcast_ref::<gst::ClockTime>()
+                .and_then(|segment| segment.to_running_time(start))
+        });
+        if let Some(running_time) = start_running_time {
+            self.push_scheduled_events(running_time).await?;
+        }
+
+        #[cfg(feature = "v1_20")]
+        let override_caps = self.take_override_caps(&mut buffer);
+        #[cfg(not(feature = "v1_20"))]
+        let override_caps = None::<gst::Caps>;
+        let stream_caps = appsrc.configured_caps.lock().unwrap().clone();
+        let override_caps = override_caps.filter(|caps| Some(caps) != stream_caps.as_ref());
+        let override_caps = override_caps.filter(|caps| {
+            let lock_caps = appsrc.settings.lock().unwrap().lock_caps;
+            if lock_caps {
+                gst::warning!(CAT, obj: self.element, "Caps are locked, ignoring {:?}", caps);
+            }
+            !lock_caps
+        });
+        // A refused caps event stays pending on the pad, so pushing
+        // the buffer fails and reports the flow error
+        if let Some(ref caps) = override_caps {
+            gst::debug!(CAT, obj: self.element, "Overriding caps with {:?}", caps);
+            if !appsrc.src_pad.push_event(gst::event::Caps::new(caps)).await {
+                gst::warning!(CAT, obj: self.element, "Failed to push caps {:?}", caps);
+            }
+        }
+
+        let res = self.push_with_retries(buffer).await;
+
+        if res.is_ok() && override_caps.is_some() {
+            if let Some(caps) = stream_caps {
+                gst::debug!(CAT, obj: self.element, "Restoring {:?}", caps);
+                if !appsrc
+                    .src_pad
+                    .push_event(gst::event::Caps::new(&caps))
+                    .await
+                {
+                    gst::warning!(CAT, obj: self.element, "Failed to restore caps {:?}", caps);
+                }
+            }
+        }
+
+        if res.is_ok() {
+            *appsrc.buffer_count.lock().unwrap() += 1;
+            if let Some(running_time) = self
+                .segment
+                .downcast_ref::<gst::ClockTime>()
+                .and_then(|segment| segment.to_running_time(position))
+            {
+                self.last_running_time = Some(running_time);
+            }
+            self.check_segment_done(position).await;
+            self.last_position = position;
+
+            self.forwarded_buffers += 1;
+            let num_buffers = appsrc.settings.lock().unwrap().num_buffers;
+            // The task stops after the EOS, so further pushes, including an
+            // application EOS, are not forwarded until the next start
+            if num_buffers >= 0 && self.forwarded_buffers >= num_buffers as u64 {
+                gst::debug!(CAT, obj: self.element, "Reached num-buffers {}", num_buffers);
+                return Err(gst::FlowError::Eos);
             }
         }
+
+        res
     }
 
-    fn end_of_stream(&self) -> bool {
-        let mut sender = self.sender.lock().unwrap();
-        let sender = match sender.as_mut() {
-            Some(sender) => sender,
-            None => return false,
+    /// Clips `buffer` to the current time segment.
+    ///
+    /// Returns `None` if the buffer is entirely outside the segment.
+    /// Buffers without a PTS and non-time segments are left untouched.
+    fn clip_to_segment(&self, mut buffer: gst::Buffer) -> Option<gst::Buffer> {
+        let Some(segment) = self.segment.downcast_ref::<gst::ClockTime>() else {
+            return Some(buffer);
         };
+        let Some(start) = buffer.pts() else {
+            return Some(buffer);
+        };
+        let stop = buffer
+            .duration()
+            .map(|duration| start.saturating_add(duration));
 
-        match sender.try_send(StreamItem::Event(gst::event::Eos::new())) {
-            Ok(_) => true,
-            Err(err) => {
-                gst::error!(CAT, imp: self, "Failed to queue EOS: {}", err);
-                false
+        let Some((clip_start, clip_stop)) = segment.clip(start, stop) else {
+            gst::log!(CAT, obj: self.element, "Dropping {:?} outside {:?}", buffer, segment);
+            return None;
+        };
+
+        if clip_start != Some(start) || clip_stop != stop {
+            gst::log!(
+                CAT,
+                obj: self.element,
+                "Clipping {:?} to {:?} - {:?}",
+                buffer,
+                clip_start,
+                clip_stop,
+            );
+            if let Some(buffer) = self.buffer_mut(&mut buffer, "clipping") {
+                buffer.set_pts(clip_start);
+                if let (Some(clip_start), Some(clip_stop)) = (clip_start, clip_stop) {
+                    buffer.set_duration(clip_stop.saturating_sub(clip_start));
+                }
             }
         }
+
+        Some(buffer)
     }
 
-    fn prepare(&self) -> Result<(), gst::ErrorMessage> {
-        gst::debug!(CAT, imp: self, "Preparing");
+    /// Removes the caps override meta from `buffer`, returning its caps.
+    #[cfg(feature = "v1_20")]
+    fn take_override_caps(&self, buffer: &mut gst::Buffer) -> Option<gst::Caps> {
+        let caps = gst::meta::CustomMeta::from_buffer(buffer, super::CAPS_META_NAME)
+            .ok()?
+            .structure()
+            .get::<gst::Caps>("caps")
+            .ok();
 
-        let settings = self.settings.lock().unwrap();
-        let context =
-            Context::acquire(&settings.context, settings.context_wait).map_err(|err| {
-                gst::error_msg!(
-                    gst::ResourceError::OpenRead,
-                    ["Failed to acquire Context: {}", err]
-                )
-            })?;
-        let max_buffers = settings.max_buffers.try_into().map_err(|err| {
-            gst::error_msg!(
-                gst::ResourceError::Settings,
-                ["Invalid max-buffers: {}, {}", settings.max_buffers, err]
+        if let Some(buffer) = self.buffer_mut(buffer, "caps meta removal") {
+            if let Ok(meta) = gst::meta::CustomMeta::from_mut_buffer(buffer, super::CAPS_META_NAME)
+            {
+                let _ = meta.remove();
+            }
+        }
+
+        caps
+    }
+
+    /// Returns `buffer` for modification, copying it if it is not writable.
+    ///
+    /// With `strict-zero-copy`, `None` is returned instead of copying and the
+    /// `change` is skipped.
+    fn buffer_mut<'a>(
+        &self,
+        buffer: &'a mut gst::Buffer,
+        change: &str,
+    ) -> Option<&'a mut gst::BufferRef> {
+        if !buffer.is_writable() && self.element.imp().settings.lock().unwrap().strict_zero_copy {
+            gst::warning!(
+                CAT,
+                obj: self.element,
+                "Skipping {} for non-writable {:?}",
+                change,
+                buffer,
+            );
+            return None;
+        }
+
+        Some(buffer.make_mut())
+    }
+
+    /// Pushes `buffer`, retrying upon the configured `retry-errors`.
+    async fn push_with_retries(
+        &self,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let appsrc = self.element.imp();
+        let (max_retries, retry_interval, retry_errors) = {
+            let settings = appsrc.settings.lock().unwrap();
+            if settings.passive {
+                gst::trace!(CAT, obj: self.element, "Passive, consuming {:?}", buffer);
+                return Ok(gst::FlowSuccess::Ok);
+            }
+            (
+                settings.max_retries,
+                settings.retry_interval,
+                settings.retry_errors,
             )
-        })?;
-        drop(settings);
+        };
 
-        *self.configured_caps.lock().unwrap() = None;
+        let mut buffer = Some(buffer);
+        let mut retries = 0;
+        loop {
+            // Only keep a reference when a retry is possible, so that the buffer
+            // remains writable downstream otherwise
+            let attempt = if retries < max_retries {
+                buffer.clone().unwrap()
+            } else {
+                buffer.take().unwrap()
+            };
 
-        let (sender, receiver) = mpsc::channel(max_buffers);
-        *self.sender.lock().unwrap() = Some(sender);
+            match appsrc.src_pad.push(attempt).await {
+                Err(err) if retries < max_retries && retry_errors.contains_flow_error(err) => {
+                    retries += 1;
+                    gst::debug!(
+                        CAT,
+                        obj: self.element,
+                        "Push failed with {:?}, retry {}/{} in {}",
+                        err,
+                        retries,
+                        max_retries,
+                        retry_interval,
+                    );
+                    timer::delay_for(retry_interval.into()).await;
+                }
+                res => return res,
+            }
+        }
+    }
 
-        self.task
-            .prepare(AppSrcTask::new(self.obj().clone(), receiver), context)
-            .block_on()?;
+    /// Signals the end of a segment seek segment once `position` reaches its stop.
+    async fn check_segment_done(&mut self, position: Option<gst::ClockTime>) {
+        if self.segment_done {
+            return;
+        }
 
-        gst::debug!(CAT, imp: self, "Prepared");
+        let Some(segment) = self.segment.downcast_ref::<gst::ClockTime>() else {
+            return;
+        };
+        if !segment.flags().contains(gst::SegmentFlags::SEGMENT) {
+            return;
+        }
+        let Some(stop) = segment.stop() else {
+            return;
+        };
+        if position.map_or(true, |position| position < stop) {
+            return;
+        }
+
+        gst::debug!(CAT, obj: self.element, "Reached segment stop {}", stop);
+        self.segment_done = true;
+
+        let appsrc = self.element.imp();
+        appsrc
+            .src_pad
+            .push_event(gst::event::SegmentDone::new(stop))
+            .await;
+        let _ = self.element.post_message(
+            gst::message::SegmentDone::builder(stop)
+                .src(&self.element)
+                .build(),
+        );
+    }
+
+    async fn push_caps(&self, caps: gst::Caps) -> Result<(), gst::FlowError> {
+        let appsrc = self.element.imp();
+
+        let caps = if caps.is_fixed() {
+            caps
+        } else {
+            let mut fixed_caps = appsrc.src_pad.gst_pad().peer_query_caps(Some(&caps));
+            if fixed_caps.is_empty() {
+                gst::error!(CAT, obj: self.element, "No common caps for {:?}", caps);
+                return Err(gst::FlowError::NotNegotiated);
+            }
+
+            fixed_caps.fixate();
+            gst::debug!(CAT, obj: self.element, "Fixated {:?} to {:?}", caps, fixed_caps);
+
+            fixed_caps
+        };
+
+        appsrc
+            .src_pad
+            .push_event(gst::event::Caps::new(&caps))
+            .await;
+
+        // The latency might be derived from the framerate
+        let old_latency = appsrc.latency();
+        *appsrc.configured_caps.lock().unwrap() = Some(caps.clone());
+        if appsrc.latency() != old_latency {
+            appsrc.post_latency_changed();
+        }
+
+        if appsrc.settings.lock().unwrap().emit_signals {
+            self.element.emit_by_name::<()>("caps-changed", &[&caps]);
+            self.query_allocation(&caps);
+        }
 
         Ok(())
     }
 
-    fn unprepare(&self) {
-        gst::debug!(CAT, imp: self, "Unpreparing");
+    /// Queries downstream for the allocation of buffers with `caps`.
+    ///
+    /// The result is notified to the application with the `allocation` signal.
+    fn query_allocation(&self, caps: &gst::Caps) {
+        let mut query = gst::query::Allocation::new(Some(caps), true);
+        if !self.element.imp().src_pad.gst_pad().peer_query(&mut query) {
+            gst::debug!(CAT, obj: self.element, "Allocation query not answered");
+            return;
+        }
 
-        *self.sender.lock().unwrap() = None;
-        self.task.unprepare().block_on().unwrap();
+        let (pool, size, min_buffers, max_buffers) = query
+            .allocation_pools()
+            .into_iter()
+            .next()
+            .unwrap_or((None, 0, 0, 0));
+        let (allocator, params) = query
+            .allocation_params()
+            .into_iter()
+            .next()
+            .unwrap_or((None, gst::AllocationParams::default()));
 
-        gst::debug!(CAT, imp: self, "Unprepared");
+        let params = gst::Structure::builder("params")
+            .field("size", size)
+            .field("min-buffers", min_buffers)
+            .field("max-buffers", max_buffers)
+            .field("flags", params.flags())
+            .field("align", params.align() as u64)
+            .field("prefix", params.prefix() as u64)
+            .field("padding", params.padding() as u64)
+            .build();
+        gst::debug!(CAT, obj: self.element, "Negotiated allocation {:?}", params);
+
+        self.element
+            .emit_by_name::<()>("allocation", &[&pool, &allocator, &params]);
     }
 
-    fn stop(&self) -> Result<(), gst::ErrorMessage> {
-        gst::debug!(CAT, imp: self, "Stopping");
-        self.task.stop().block_on()?;
-        gst::debug!(CAT, imp: self, "Stopped");
+    async fn push_prelude(&mut self, item: &StreamItem) -> Result<(), gst::FlowError> {
+        let appsrc = self.element.imp();
+
+        if self.need_initial_events {
+            gst::debug!(CAT, obj: self.element, "Pushing initial events");
+
+            self.stream_id = format!("{:016x}", self.stream_id_rng.gen::<u64>());
+            let stream_flags = {
+                let settings = appsrc.settings.lock().unwrap();
+                if settings.sparse {
+                    settings.stream_flags | gst::StreamFlags::SPARSE
+                } else {
+                    settings.stream_flags
+                }
+            };
+            let stream_start_evt = gst::event::StreamStart::builder(&self.stream_id)
+                .group_id(gst::GroupId::next())
+                .flags(stream_flags)
+                .build();
+            self.post_stream_message("ts-appsrc-stream-start");
+            appsrc.src_pad.push_event(stream_start_evt).await;
+            *appsrc.buffer_count.lock().unwrap() = 0;
+
+            // Don't announce the configured caps if they are about to be replaced
+            let caps = match item {
+                StreamItem::CapsBuffer(caps, _) => Some(caps.clone()),
+                _ => appsrc.settings.lock().unwrap().caps.clone(),
+            };
+            if let Some(caps) = caps {
+                self.push_caps(caps).await?;
+            }
+
+            self.need_initial_events = false;
+        }
+
+        let renegotiated_caps = appsrc.renegotiated_caps.lock().unwrap().take();
+        if let Some(caps) = renegotiated_caps {
+            gst::debug!(CAT, obj: self.element, "Pushing renegotiated caps");
+            self.push_caps(caps).await?;
+        }
+
+        if self.need_segment {
+            // A segment pushed by the application replaces the default one
+            let is_segment = matches!(
+                item,
+                StreamItem::Event(event) if event.type_() == gst::EventType::Segment
+            );
+            if !is_segment {
+                let pending_segment = appsrc.pending_segment.lock().unwrap().take();
+                let segment = pending_segment.unwrap_or_else(|| {
+                    let settings = appsrc.settings.lock().unwrap();
+                    // Only the first segment of a run continues the previous timeline
+                    let persisted_running_time =
+                        appsrc.persisted_running_time.lock().unwrap().take();
+                    match persisted_running_time {
+                        Some(base)
+                            if settings.persist_segment && settings.format == gst::Format::Time =>
+                        {
+                            gst::debug!(CAT, obj: self.element, "Resuming at {}", base);
+                            let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+                            segment.set_base(base);
+                            segment.upcast()
+                        }
+                        _ => {
+                            let mut segment = gst::Segment::new();
+                            segment.reset_with_format(settings.format);
+                            segment
+                        }
+                    }
+                });
+                let mut segment_event = gst::event::Segment::builder(&segment);
+                if let Some(seqnum) = appsrc.pending_segment_seqnum.lock().unwrap().take() {
+                    segment_event = segment_event.seqnum(seqnum);
+                }
+                appsrc.src_pad.push_event(segment_event.build()).await;
+                self.set_segment(segment);
+            }
+
+            self.need_segment = false;
+        }
+
         Ok(())
     }
 
-    fn start(&self) -> Result<(), gst::ErrorMessage> {
-        gst::debug!(CAT, imp: self, "Starting");
-        self.task.start().block_on()?;
-        gst::debug!(CAT, imp: self, "Started");
-        Ok(())
+    fn set_segment(&mut self, segment: gst::Segment) {
+        *self.element.imp().current_segment.lock().unwrap() = Some(segment.clone());
+        self.segment = segment;
+        self.segment_done = false;
+    }
+
+    /// Waits for `min-fill` buffers to be queued before pushing, unless an EOS is queued.
+    ///
+    /// The buffers of the `buffer-stream` don't count, so the fill is skipped
+    /// for them. Once filled, the queued buffers are pushed as fast as
+    /// downstream accepts them: with `max-bitrate`, they are paced instead, so the
+    /// fill is preserved as a margin against jitter rather than drained in a burst.
+    async fn wait_fill(&self) {
+        let appsrc = self.element.imp();
+        if appsrc.buffer_stream.lock().unwrap().is_some() {
+            return;
+        }
+
+        let (min_fill, min_fill_percent) = {
+            let settings = appsrc.settings.lock().unwrap();
+            (settings.min_fill, settings.min_fill_percent)
+        };
+        let capacity = appsrc.arrivals.lock().unwrap().capacity as u64;
+        let percent_fill = (capacity * min_fill_percent as u64 + 99) / 100;
+        // The queue can't hold more than its capacity
+        let min_fill = (min_fill as u64).max(percent_fill).min(capacity) as usize;
+        if min_fill == 0 {
+            return;
+        }
+
+        gst::debug!(CAT, obj: self.element, "Waiting for {} buffers", min_fill);
+        future::poll_fn(|cx| {
+            let mut arrivals = appsrc.arrivals.lock().unwrap();
+            if arrivals.queue.len() >= min_fill {
+                gst::debug!(CAT, obj: self.element, "Reached min-fill");
+                return Poll::Ready(());
+            }
+            if arrivals.eos_queued {
+                gst::debug!(CAT, obj: self.element, "EOS queued, not waiting for min-fill");
+                return Poll::Ready(());
+            }
+
+            arrivals.fill_waker = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Applies a `context-wait` changed after the Context was acquired.
+    ///
+    /// The Context keeps throttling with the wait it was acquired with,
+    /// so a greater wait is emulated by letting items pile up for that duration.
+    async fn throttle(&self) {
+        let context_wait = self.element.imp().settings.lock().unwrap().context_wait;
+        let acquired_wait = Context::current().map_or(Duration::ZERO, |ctx| ctx.wait_duration());
+        if context_wait > acquired_wait {
+            gst::trace!(CAT, obj: self.element, "Throttling for {:?}", context_wait);
+            timer::delay_for(context_wait).await;
+        }
+    }
+
+    async fn push_event(&mut self, event: gst::Event) -> Result<gst::FlowSuccess, gst::FlowError> {
+        match event.view() {
+            gst::EventView::Eos(_) => {
+                // Let the caller push the event
+                return Err(gst::FlowError::Eos);
+            }
+            gst::EventView::Segment(ev) => self.set_segment(ev.segment().clone()),
+            _ => (),
+        }
+
+        gst::log!(CAT, obj: self.element, "Forwarding {:?}", event);
+        self.element.imp().src_pad.push_event(event).await;
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    /// Pushes a GAP event covering the time since the previous buffer, see `sparse`.
+    ///
+    /// Before the first buffer, the gap starts at the segment start.
+    async fn push_sparse_gap(&self, buffer: &gst::Buffer) {
+        let Some(segment) = self.segment.downcast_ref::<gst::ClockTime>() else {
+            return;
+        };
+        let Some(pts) = buffer.pts() else {
+            return;
+        };
+        let Some(gap_start) = self.last_position.or(segment.start()) else {
+            return;
+        };
+        if pts <= gap_start {
+            return;
+        }
+
+        let gap = gst::event::Gap::builder(gap_start)
+            .duration(pts - gap_start)
+            .build();
+        gst::log!(CAT, obj: self.element, "Filling sparse stream with {:?}", gap);
+        self.element.imp().src_pad.push_event(gap).await;
+    }
+
+    /// Holds `event` until a buffer starting at or after `running_time` is pushed.
+    async fn schedule_event(
+        &mut self,
+        running_time: gst::ClockTime,
+        event: gst::Event,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        if let Some(last_running_time) = self
+            .last_running_time
+            .filter(|last_running_time| running_time < *last_running_time)
+        {
+            gst::warning!(
+                CAT,
+                obj: self.element,
+                "Pushing {:?} now, scheduled at {} but stream is already at {}",
+                event,
+                running_time,
+                last_running_time,
+            );
+            return self.push_event(event).await;
+        }
+
+        gst::debug!(CAT, obj: self.element, "Scheduling {:?} at {}", event, running_time);
+        let idx = self
+            .scheduled_events
+            .partition_point(|(scheduled_at, _)| *scheduled_at <= running_time);
+        self.scheduled_events.insert(idx, (running_time, event));
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    /// Pushes the scheduled events due at `running_time`.
+    async fn push_scheduled_events(
+        &mut self,
+        running_time: gst::ClockTime,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        while self
+            .scheduled_events
+            .front()
+            .is_some_and(|(scheduled_at, _)| *scheduled_at <= running_time)
+        {
+            let (scheduled_at, event) = self.scheduled_events.pop_front().unwrap();
+            gst::log!(CAT, obj: self.element, "Reached {} for {:?}", scheduled_at, event);
+            self.push_event(event).await?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    async fn push_item(&mut self, item: StreamItem) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj: self.element, "Handling {:?}", item);
+
+        if !matches!(item, StreamItem::ResetStream) {
+            self.push_prelude(&item).await?;
+        }
+
+        let appsrc = self.element.imp();
+
+        match item {
+            StreamItem::Buffer(buffer)
+            | StreamItem::Filler(buffer)
+            | StreamItem::Streamed(buffer) => self.push_buffer(buffer).await,
+            StreamItem::DeadlineBuffer(deadline, buffer) => {
+                let running_time = self.element.current_running_time();
+                if running_time.is_some_and(|running_time| running_time > deadline) {
+                    gst::debug!(
+                        CAT,
+                        obj: self.element,
+                        "Dropping {:?}, deadline {} passed at {}",
+                        buffer,
+                        deadline,
+                        running_time.display(),
+                    );
+                    appsrc.stats.lock().unwrap().deadline_drops += 1;
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+
+                self.push_buffer(buffer).await
+            }
+            StreamItem::CapsBuffer(caps, buffer) => {
+                // Current caps might have been fixated from the requested caps
+                let caps_changed = appsrc
+                    .configured_caps
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(true, |current_caps| !current_caps.is_subset(&caps));
+                if caps_changed {
+                    gst::debug!(CAT, obj: self.element, "Pushing new {:?}", caps);
+                    self.push_caps(caps).await?;
+                }
+
+                self.push_buffer(buffer).await
+            }
+            StreamItem::Event(event) => self.push_event(event).await,
+            StreamItem::Events(events) => {
+                gst::log!(CAT, obj: self.element, "Forwarding {} events", events.len());
+                for event in events {
+                    self.push_event(event).await?;
+                }
+                Ok(gst::FlowSuccess::Ok)
+            }
+            StreamItem::ScheduledEvent(running_time, event) => {
+                self.schedule_event(running_time, event).await
+            }
+            StreamItem::Negotiate(ack_tx) => {
+                let negotiated = match appsrc.src_pad.gst_pad().peer() {
+                    Some(peer) => {
+                        let configured_caps = appsrc.configured_caps.lock().unwrap().clone();
+                        configured_caps.map_or(true, |caps| peer.current_caps() == Some(caps))
+                    }
+                    None => false,
+                };
+                gst::debug!(CAT, obj: self.element, "Negotiated: {}", negotiated);
+
+                let _ = ack_tx.send(negotiated);
+                Ok(gst::FlowSuccess::Ok)
+            }
+            StreamItem::ResetStream => {
+                self.reset_stream();
+                Ok(gst::FlowSuccess::Ok)
+            }
+        }
+    }
+}
+
+impl TaskImpl for AppSrcTask {
+    type Item = StreamItem;
+
+    fn prepare(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            self.set_task_state(TaskState::Prepared);
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn unprepare(&mut self) -> BoxFuture<'_, ()> {
+        async move {
+            self.set_task_state(TaskState::Unprepared);
+        }
+        .boxed()
+    }
+
+    fn start(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            self.set_task_state(TaskState::Started);
+            Ok(())
+        }
+        .boxed()
     }
 
-    fn pause(&self) -> Result<(), gst::ErrorMessage> {
-        gst::debug!(CAT, imp: self, "Pausing");
-        self.task.pause().block_on()?;
-        gst::debug!(CAT, imp: self, "Paused");
-        Ok(())
-    }
-}
+    fn pause(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            if self.task_state == TaskState::Flushing {
+                self.set_task_state(TaskState::PausedFlushing);
+            } else {
+                self.set_task_state(TaskState::Paused);
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn flush_stop(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            if self.task_state == TaskState::PausedFlushing {
+                self.set_task_state(TaskState::Paused);
+            } else {
+                self.set_task_state(TaskState::Started);
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn try_next(&mut self) -> BoxFuture<'_, Result<StreamItem, gst::FlowError>> {
+        async move {
+            loop {
+                if let Some(item) = self.reordered.pop_front() {
+                    return Ok(item);
+                }
+
+                if self.need_fill {
+                    self.wait_fill().await;
+                    self.need_fill = false;
+                }
+
+                let window: Duration = self
+                    .element
+                    .imp()
+                    .settings
+                    .lock()
+                    .unwrap()
+                    .reorder_window
+                    .into();
+                if let Some(item) = self.reorder.pop_due(window) {
+                    return Ok(item);
+                }
+
+                if let Some((deadline, _)) = self.pending_eos {
+                    if deadline <= Instant::now() {
+                        gst::debug!(CAT, obj: self.element, "EOS debounce elapsed");
+                        return Ok(self.pending_eos.take().unwrap().1);
+                    }
+                }
+
+                let deadline = self
+                    .reorder
+                    .next_deadline(window)
+                    .into_iter()
+                    .chain(self.pending_eos.as_ref().map(|(deadline, _)| *deadline))
+                    .min();
+                let item = match deadline {
+                    Some(deadline) => {
+                        // Wait for the next item, but not past the release of a held item
+                        let delay =
+                            timer::delay_for(deadline.saturating_duration_since(Instant::now()));
+                        let next_item = self.next_item();
+                        futures::pin_mut!(delay, next_item);
+                        match future::select(next_item, delay).await {
+                            future::Either::Left((res, _)) => res?,
+                            future::Either::Right(_) => continue,
+                        }
+                    }
+                    None => self.next_item().await?,
+                };
+
+                if matches!(
+                    item,
+                    StreamItem::Buffer(_)
+                        | StreamItem::CapsBuffer(..)
+                        | StreamItem::DeadlineBuffer(..)
+                ) {
+                    let appsrc = self.element.imp();
+                    let (arrival, level) = {
+                        let mut arrivals = appsrc.arrivals.lock().unwrap();
+                        (arrivals.queue.pop_front(), arrivals.queue.len())
+                    };
+                    appsrc.level_cond.notify_all();
+                    appsrc.update_buffering(level);
+
+                    let (do_stats, max_buffer_age) = {
+                        let settings = appsrc.settings.lock().unwrap();
+                        (settings.do_stats, settings.max_buffer_age)
+                    };
+                    let age = arrival.map(|arrival| arrival.elapsed());
+
+                    // Buffers with caps are kept so as not to lose the caps change
+                    if max_buffer_age > gst::ClockTime::ZERO
+                        && !matches!(item, StreamItem::CapsBuffer(..))
+                        && age.is_some_and(|age| age > max_buffer_age.into())
+                    {
+                        gst::debug!(
+                            CAT,
+                            obj: self.element,
+                            "Dropping {:?}, queued for {:?}",
+                            item,
+                            age.unwrap(),
+                        );
+                        appsrc.stats.lock().unwrap().age_drops += 1;
+                        self.need_discont = true;
+                        continue;
+                    }
+
+                    if do_stats {
+                        if let Some(age) = age {
+                            appsrc.stats.lock().unwrap().add_dwell(age);
+                        }
+                    }
+                }
+
+                if self.pending_eos.is_some() && matches!(item, StreamItem::Filler(_)) {
+                    // Don't fill past the EOS
+                    continue;
+                }
+
+                if let Some((_, eos)) = self.pending_eos.take() {
+                    if matches!(item, StreamItem::ResetStream) {
+                        gst::debug!(CAT, obj: self.element, "Canceling EOS upon stream reset");
+                    } else {
+                        // Items queued after the EOS are handled after it
+                        self.reordered.push_back(eos);
+                        self.reordered.push_back(item);
+                        continue;
+                    }
+                }
+
+                if item.is_eos() {
+                    let eos_debounce = self.element.imp().settings.lock().unwrap().eos_debounce;
+                    if !eos_debounce.is_zero() {
+                        gst::debug!(CAT, obj: self.element, "Holding EOS for {:?}", eos_debounce);
+                        self.reorder.drain_into(&mut self.reordered);
+                        self.pending_eos = Some((Instant::now() + eos_debounce, item));
+                        continue;
+                    }
+                }
+
+                if window.is_zero() && self.reorder.is_empty() {
+                    return Ok(item);
+                }
+
+                match item {
+                    StreamItem::Buffer(buffer) if !window.is_zero() && buffer.pts().is_some() => {
+                        let pts = buffer.pts().unwrap();
+                        if self.reorder.is_late(pts) {
+                            gst::warning!(
+                                CAT,
+                                obj: self.element,
+                                "Dropping buffer at {} arriving after the reorder window",
+                                pts,
+                            );
+                            continue;
+                        }
+                        self.reorder.hold(pts, StreamItem::Buffer(buffer));
+                    }
+                    item => {
+                        // Other items can't be reordered, release the held buffers first
+                        self.reorder.drain_into(&mut self.reordered);
+                        self.reordered.push_back(item);
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    fn handle_item(&mut self, item: StreamItem) -> BoxFuture<'_, Result<(), gst::FlowError>> {
+        async move {
+            // Keep track of the item for error-detailed
+            let buffer = item.buffer().cloned();
+            let item_caps = match item {
+                StreamItem::CapsBuffer(ref caps, _) => Some(caps.clone()),
+                _ => None,
+            };
+
+            let res = self.push_item(item).await;
+            let not_linked_policy = self
+                .element
+                .imp()
+                .settings
+                .lock()
+                .unwrap()
+                .not_linked_policy;
+            match res {
+                Ok(_) => {
+                    gst::log!(CAT, obj: self.element, "Successfully pushed item");
+                }
+                Err(gst::FlowError::Eos) => {
+                    gst::debug!(CAT, obj: self.element, "EOS");
+                    self.post_stream_message("ts-appsrc-eos");
+                    let appsrc = self.element.imp();
+                    // The stream won't reach their running time
+                    for (scheduled_at, event) in std::mem::take(&mut self.scheduled_events) {
+                        gst::warning!(
+                            CAT,
+                            obj: self.element,
+                            "Pushing {:?} scheduled at {} before EOS",
+                            event,
+                            scheduled_at,
+                        );
+                        appsrc.src_pad.push_event(event).await;
+                    }
+                    appsrc.src_pad.push_event(gst::event::Eos::new()).await;
+                }
+                Err(gst::FlowError::Flushing) => {
+                    gst::debug!(CAT, obj: self.element, "Flushing");
+                }
+                Err(gst::FlowError::NotLinked) if not_linked_policy == NotLinkedPolicy::Drop => {
+                    gst::debug!(CAT, obj: self.element, "Not linked, dropping item");
+                    return Ok(());
+                }
+                Err(gst::FlowError::NotLinked) if not_linked_policy == NotLinkedPolicy::Pause => {
+                    gst::debug!(CAT, obj: self.element, "Not linked, pausing");
+                }
+                Err(err) => {
+                    gst::error!(CAT, obj: self.element, "Got error {}", err);
+                    gst::element_error!(
+                        &self.element,
+                        gst::StreamError::Failed,
+                        ("Internal data stream error"),
+                        ["streaming stopped, reason {}", err]
+                    );
+
+                    let appsrc = self.element.imp();
+                    let code = if err == gst::FlowError::NotNegotiated {
+                        ErrorCode::NotNegotiated
+                    } else {
+                        ErrorCode::FlowError
+                    };
+                    // Negotiation might have failed before any caps were sent
+                    let caps = item_caps
+                        .or_else(|| appsrc.configured_caps.lock().unwrap().clone())
+                        .or_else(|| appsrc.settings.lock().unwrap().caps.clone());
+                    appsrc.error_detailed(
+                        code,
+                        &format!("streaming stopped, reason {err}"),
+                        caps.as_ref(),
+                        buffer.as_ref(),
+                    );
+                }
+            }
+
+            res.map(drop)
+        }
+        .boxed()
+    }
+
+    fn handle_loop_error(&mut self, err: gst::FlowError) -> BoxFuture<'_, task::Trigger> {
+        async move {
+            let trigger = match err {
+                gst::FlowError::Flushing => task::Trigger::FlushStart,
+                gst::FlowError::Eos => task::Trigger::Stop,
+                gst::FlowError::NotLinked => {
+                    let not_linked_policy = self
+                        .element
+                        .imp()
+                        .settings
+                        .lock()
+                        .unwrap()
+                        .not_linked_policy;
+                    if not_linked_policy == NotLinkedPolicy::Pause {
+                        // Resumed upon the next reconfigure event
+                        task::Trigger::Pause
+                    } else {
+                        task::Trigger::Error
+                    }
+                }
+                _ => task::Trigger::Error,
+            };
+
+            if trigger == task::Trigger::Error {
+                self.set_task_state(TaskState::Error);
+            }
+
+            trigger
+        }
+        .boxed()
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            gst::log!(CAT, obj: self.element, "Stopping task");
+
+            self.flush();
+            self.drop_buffer_stream();
+            self.token_bucket.reset();
+            self.scheduled_events.clear();
+            self.need_fill = true;
+            self.need_initial_events = true;
+            self.need_segment = true;
+            self.need_discont = true;
+            self.forwarded_buffers = 0;
+            self.last_position = None;
+            self.reorder.reset_last_pts();
+
+            let appsrc = self.element.imp();
+            *appsrc.timestamp_segment.lock().unwrap() = None;
+            if appsrc.settings.lock().unwrap().persist_segment {
+                if let Some(running_time) = self.last_running_time.take() {
+                    *appsrc.persisted_running_time.lock().unwrap() = Some(running_time);
+                }
+            }
+
+            self.set_task_state(TaskState::Stopped);
+
+            gst::log!(CAT, obj: self.element, "Task stopped");
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn flush_start(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            gst::log!(CAT, obj: self.element, "Starting task flush");
+
+            let flush_mode = self.element.imp().settings.lock().unwrap().flush_mode;
+            match flush_mode {
+                FlushMode::Cancel => self.flush(),
+                FlushMode::Pause => {
+                    gst::debug!(CAT, obj: self.element, "Keeping queued items");
+                    // Unblock push-buffer-wait-level
+                    self.element.imp().arrivals.lock().unwrap().flushes += 1;
+                    self.element.imp().level_cond.notify_all();
+                }
+            }
+            self.drop_buffer_stream();
+            // A flush cancels the EOS, even when keeping the queued items
+            self.pending_eos = None;
+            // flush-to-segment sets the new segment once the flush is started
+            *self.element.imp().timestamp_segment.lock().unwrap() = None;
+            self.token_bucket.reset();
+            self.scheduled_events.clear();
+            self.need_fill = true;
+            self.need_segment = true;
+            self.need_discont = true;
+            self.forwarded_buffers = 0;
+            self.last_position = None;
+            self.reorder.reset_last_pts();
+            *self.element.imp().buffer_count.lock().unwrap() = 0;
+            self.element.imp().stats.lock().unwrap().reset_intervals();
+
+            if self.task_state == TaskState::Paused {
+                self.set_task_state(TaskState::PausedFlushing);
+            } else {
+                self.set_task_state(TaskState::Flushing);
+            }
+
+            gst::log!(CAT, obj: self.element, "Task flush started");
+            Ok(())
+        }
+        .boxed()
+    }
+}
+
+#[derive(Debug)]
+pub struct AppSrc {
+    src_pad: PadSrc,
+    task: Task,
+    sender: Mutex<Option<Box<dyn ItemSender<StreamItem>>>>,
+    configured_caps: Mutex<Option<gst::Caps>>,
+    buffer_count: Mutex<u64>,
+    clock_source_offset: Mutex<Option<gst::ClockTime>>,
+    pending_segment: Mutex<Option<gst::Segment>>,
+    /// The seqnum of the seek which led to the `pending_segment`.
+    pending_segment_seqnum: Mutex<Option<gst::Seqnum>>,
+    timestamp_segment: Mutex<Option<gst::FormattedSegment<gst::ClockTime>>>,
+    stats: Mutex<Stats>,
+    persisted_running_time: Mutex<Option<gst::ClockTime>>,
+    renegotiated_caps: Mutex<Option<gst::Caps>>,
+    /// The latency configured by the last Latency event.
+    configured_latency: Mutex<gst::ClockTime>,
+    /// The segment sent downstream, for `dump-state` and the `segment` property.
+    current_segment: Mutex<Option<gst::Segment>>,
+    spill: Mutex<Option<Spill<StreamItem>>>,
+    arrivals: Mutex<Arrivals>,
+    level_cond: Condvar,
+    provided_context: Mutex<Option<Context>>,
+    running_context: Mutex<Option<Context>>,
+    shutting_down: Mutex<bool>,
+    /// Whether downstream asked to stop pushing, see `THROTTLE_EVENT_NAME`.
+    throttled: Mutex<bool>,
+    full_drops: Mutex<FullDrops>,
+    buffering: Mutex<Buffering>,
+    buffer_stream: Mutex<Option<BufferStreamFactory>>,
+    file_reader: Mutex<Option<FileReader>>,
+    settings: Mutex<Settings>,
+}
+
+impl AppSrc {
+    /// Emits `error-detailed` if `emit-signals` is enabled.
+    fn error_detailed(
+        &self,
+        code: ErrorCode,
+        message: &str,
+        caps: Option<&gst::Caps>,
+        buffer: Option<&gst::Buffer>,
+    ) {
+        if !self.settings.lock().unwrap().emit_signals {
+            return;
+        }
+
+        let mut details = gst::Structure::builder("ts-appsrc-error")
+            .field("code", code)
+            .field("message", message)
+            .build();
+        if let Some(caps) = caps {
+            details.set("caps", caps);
+        }
+        if let Some(buffer) = buffer {
+            details.set("buffer-size", buffer.size() as u64);
+            if let Some(pts) = buffer.pts() {
+                details.set("buffer-pts", pts);
+            }
+            if let Some(dts) = buffer.dts() {
+                details.set("buffer-dts", dts);
+            }
+        }
+        gst::debug!(CAT, imp: self, "Reporting {:?}", details);
+
+        self.obj().emit_by_name::<()>("error-detailed", &[&details]);
+    }
+
+    pub(super) fn set_provided_context(&self, context: Option<Context>) {
+        *self.provided_context.lock().unwrap() = context;
+    }
+
+    pub(super) fn running_context(&self) -> Option<Context> {
+        self.running_context.lock().unwrap().clone()
+    }
+
+    pub(super) fn set_buffer_stream(&self, make_stream: Option<Box<MakeBufferStream>>) {
+        gst::debug!(CAT, imp: self, "Setting buffer stream: {}", make_stream.is_some());
+        *self.buffer_stream.lock().unwrap() =
+            make_stream.map(|make_stream| BufferStreamFactory(Arc::from(make_stream)));
+    }
+
+    pub(super) fn flush(&self) -> Result<(), glib::BoolError> {
+        let state = self.task.state();
+        if state != TaskState::Started && state != TaskState::Paused {
+            return Err(glib::bool_error!("Can't flush in state {:?}", state));
+        }
+
+        gst::debug!(CAT, imp: self, "Flushing");
+
+        self.task
+            .flush_start()
+            .await_maybe_on_context()
+            .map_err(|err| glib::bool_error!("Failed to start flush: {}", err))?;
+
+        *self.pending_segment.lock().unwrap() = None;
+        *self.pending_segment_seqnum.lock().unwrap() = None;
+
+        self.task
+            .flush_stop()
+            .await_maybe_on_context()
+            .map_err(|err| glib::bool_error!("Failed to stop flush: {}", err))?;
+
+        Ok(())
+    }
+
+    fn push_buffer(&self, buffer: gst::Buffer) -> bool {
+        self.queue_buffer(buffer, None, true)
+    }
+
+    #[cfg(feature = "v1_20")]
+    fn push_buffer_meta(&self, mut buffer: gst::Buffer, structure: gst::Structure) -> bool {
+        {
+            let buffer = buffer.make_mut();
+            let mut meta = match gst::meta::CustomMeta::add(buffer, super::META_NAME) {
+                Ok(meta) => meta,
+                Err(err) => {
+                    gst::error!(CAT, imp: self, "Failed to add meta: {}", err);
+                    return false;
+                }
+            };
+
+            let s = meta.mut_structure();
+            for (field, value) in structure.iter() {
+                s.set_value(field, value.clone());
+            }
+        }
+
+        self.queue_buffer(buffer, None, true)
+    }
+
+    #[cfg(not(feature = "v1_20"))]
+    fn push_buffer_meta(&self, _buffer: gst::Buffer, _structure: gst::Structure) -> bool {
+        gst::error!(CAT, imp: self, "Custom metas require the v1_20 feature");
+        false
+    }
+
+    fn push_buffer_protected(&self, mut buffer: gst::Buffer, info: gst::Structure) -> bool {
+        gst::ProtectionMeta::add(buffer.make_mut(), info);
+
+        self.queue_buffer(buffer, None, true)
+    }
+
+    fn push_buffer_deadline(&self, buffer: gst::Buffer, deadline: gst::ClockTime) -> bool {
+        self.queue_buffer_with_deadline(buffer, None, Some(deadline), true)
+    }
+
+    fn push_buffer_wait_level(&self, buffer: gst::Buffer, level: u32) -> bool {
+        if Context::is_context_thread() {
+            gst::error!(CAT, imp: self, "Can't wait for the queue level on a Context thread");
+            return false;
+        }
+
+        if !self.queue_buffer(buffer, None, true) {
+            return false;
+        }
+
+        let mut arrivals = self.arrivals.lock().unwrap();
+        let flushes = arrivals.flushes;
+        loop {
+            if arrivals.flushes != flushes {
+                gst::debug!(CAT, imp: self, "Flushed while waiting for level");
+                return false;
+            }
+            if arrivals.queue.len() <= level as usize {
+                return true;
+            }
+
+            arrivals = self.level_cond.wait(arrivals).unwrap();
+        }
+    }
+
+    fn push_bytes(&self, bytes: glib::Bytes) -> bool {
+        // Wraps the bytes without copying them, the buffer keeps a reference
+        self.queue_buffer(gst::Buffer::from_slice(bytes), None, true)
+    }
+
+    fn push_buffer_with_caps(&self, caps: gst::Caps, buffer: gst::Buffer) -> bool {
+        self.queue_buffer(buffer, Some(caps), true)
+    }
+
+    fn push_buffer_full_meta(
+        &self,
+        mut buffer: gst::Buffer,
+        pts: Option<gst::ClockTime>,
+        dts: Option<gst::ClockTime>,
+        duration: Option<gst::ClockTime>,
+        offset: u64,
+        offset_end: u64,
+    ) -> bool {
+        {
+            let buffer = buffer.make_mut();
+            if pts.is_some() {
+                buffer.set_pts(pts);
+            }
+            if dts.is_some() {
+                buffer.set_dts(dts);
+            }
+            if duration.is_some() {
+                buffer.set_duration(duration);
+            }
+            if offset != gst::BUFFER_OFFSET_NONE {
+                buffer.set_offset(offset);
+            }
+            if offset_end != gst::BUFFER_OFFSET_NONE {
+                buffer.set_offset_end(offset_end);
+            }
+        }
+
+        // The application described the buffer, don't override it with do-timestamp
+        self.queue_buffer(buffer, None, false)
+    }
+
+    /// Whether `caps` would change the caps sent downstream while they are locked.
+    fn rejects_caps_change(&self, lock_caps: bool, caps: &gst::Caps) -> bool {
+        if !lock_caps {
+            return false;
+        }
+
+        match *self.configured_caps.lock().unwrap() {
+            // The configured caps are fixed, so this only accepts the same caps
+            Some(ref current_caps) if !caps.is_subset(current_caps) => {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Rejecting {:?}, caps are locked to {:?}",
+                    caps,
+                    current_caps,
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Emits `compute-timestamp` if `emit-signals` is enabled and a handler is connected.
+    fn compute_timestamp(&self, buffer: &gst::Buffer) -> Option<gst::Structure> {
+        if !self.settings.lock().unwrap().emit_signals {
+            return None;
+        }
+
+        let obj = self.obj();
+        let signal_id = glib::subclass::signal::SignalId::lookup(
+            "compute-timestamp",
+            super::AppSrc::static_type(),
+        )
+        .unwrap();
+        if !glib::signal::signal_has_handler_pending(&*obj, signal_id, None, false) {
+            return None;
+        }
+
+        let running_time = obj
+            .current_running_time()
+            .map_or(u64::MAX, gst::ClockTime::nseconds);
+        obj.emit_by_name::<Option<gst::Structure>>("compute-timestamp", &[buffer, &running_time])
+    }
+
+    /// Emits `upstream-event` if `emit-signals` is enabled and a handler is connected.
+    fn unhandled_upstream_event(&self, event: &gst::Event) -> bool {
+        if !self.settings.lock().unwrap().emit_signals {
+            return false;
+        }
+
+        let obj = self.obj();
+        let signal_id = glib::subclass::signal::SignalId::lookup(
+            "upstream-event",
+            super::AppSrc::static_type(),
+        )
+        .unwrap();
+        if !glib::signal::signal_has_handler_pending(&*obj, signal_id, None, false) {
+            return false;
+        }
+
+        gst::debug!(CAT, imp: self, "Forwarding {:?} to the application", event);
+        obj.emit_by_name::<bool>("upstream-event", &[event])
+    }
+
+    fn push_buffer_get_timestamp(&self, buffer: gst::Buffer) -> Option<gst::ClockTime> {
+        self.try_queue_buffer(buffer, None, None, true)
+            .ok()
+            .flatten()
+    }
+
+    fn queue_buffer(
+        &self,
+        buffer: gst::Buffer,
+        caps: Option<gst::Caps>,
+        do_timestamp: bool,
+    ) -> bool {
+        self.queue_buffer_with_deadline(buffer, caps, None, do_timestamp)
+    }
+
+    fn queue_buffer_with_deadline(
+        &self,
+        buffer: gst::Buffer,
+        caps: Option<gst::Caps>,
+        deadline: Option<gst::ClockTime>,
+        do_timestamp: bool,
+    ) -> bool {
+        self.try_queue_buffer(buffer, caps, deadline, do_timestamp)
+            .is_ok()
+    }
+
+    /// Queues `buffer`, returning its PTS once timestamped.
+    fn try_queue_buffer(
+        &self,
+        mut buffer: gst::Buffer,
+        caps: Option<gst::Caps>,
+        deadline: Option<gst::ClockTime>,
+        do_timestamp: bool,
+    ) -> Result<Option<gst::ClockTime>, ()> {
+        // Emitted before locking, the handler might call back into the element
+        let computed = if do_timestamp {
+            self.compute_timestamp(&buffer)
+        } else {
+            None
+        };
+
+        let state = self.task.lock_state();
+        if *state != TaskState::Started && *state != TaskState::Paused {
+            gst::debug!(CAT, imp: self, "Rejecting buffer due to element state");
+            return Err(());
+        }
+        // Buffers pushed while PAUSED are queued until PLAYING by default
+        if *state == TaskState::Paused
+            && self.settings.lock().unwrap().reject_when_paused
+            && self.obj().current_state() != gst::State::Playing
+        {
+            gst::debug!(CAT, imp: self, "Rejecting buffer while paused");
+            return Err(());
+        }
+
+        let settings = self.settings.lock().unwrap();
+        if let Some(ref caps) = caps {
+            if self.rejects_caps_change(settings.lock_caps, caps) {
+                return Err(());
+            }
+        }
+        if settings.max_buffer_size > 0 && buffer.size() as u64 > settings.max_buffer_size {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "Rejecting buffer of {} bytes, max-buffer-size is {} bytes",
+                buffer.size(),
+                settings.max_buffer_size,
+            );
+            return Err(());
+        }
+        // Keep the empty buffers carrying caps, dropping them would lose the caps change
+        if settings.drop_empty_buffers && buffer.size() == 0 && caps.is_none() {
+            gst::debug!(CAT, imp: self, "Dropping empty {:?}", buffer);
+            // Not queued, so there is no timestamp to report
+            return Ok(None);
+        }
+        if let Some(ref timestamps) = computed {
+            gst::log!(CAT, imp: self, "Computed timestamps {:?}", timestamps);
+            let buffer = buffer.make_mut();
+            buffer.set_pts(timestamps.get::<gst::ClockTime>("pts").ok());
+            buffer.set_dts(timestamps.get::<gst::ClockTime>("dts").ok());
+        }
+        // Sparse streams carry their own timing
+        let do_timestamp = do_timestamp
+            && computed.is_none()
+            && !settings.sparse
+            && settings.do_timestamp
+            && match settings.do_timestamp_mode {
+                DoTimestampMode::Always => true,
+                DoTimestampMode::IfMissing => buffer.pts().is_none() && buffer.dts().is_none(),
+                DoTimestampMode::Never => false,
+            };
+        if do_timestamp {
+            let elem = self.obj();
+            if let Some(clock) = elem.clock() {
+                let base_time = elem.base_time();
+                let now = clock.time();
+                // The clock can transiently be behind the base time, e.g. right after
+                // the base time was distributed or upon a clock adjustment
+                let mut running_time = match now.opt_checked_sub(base_time) {
+                    Ok(running_time) => running_time,
+                    Err(_) => {
+                        gst::warning!(
+                            CAT,
+                            imp: self,
+                            "Clock time {} is before base time {}, clamping running time to 0",
+                            now.display(),
+                            base_time.display(),
+                        );
+                        Some(gst::ClockTime::ZERO)
+                    }
+                };
+                match settings.clock_source {
+                    ClockSource::Pipeline => (),
+                    ClockSource::Monotonic => {
+                        running_time = running_time.and_then(|running_time| {
+                            self.clock_source_running_time(&MONOTONIC_CLOCK, running_time)
+                        });
+                    }
+                    ClockSource::Realtime => {
+                        running_time = running_time.and_then(|running_time| {
+                            self.clock_source_running_time(&REALTIME_CLOCK, running_time)
+                        });
+                    }
+                }
+                let running_time = running_time
+                    .map(|running_time| running_time.saturating_sub(settings.capture_latency));
+
+                // Buffers following a flush-to-segment are relative to the new segment
+                let timestamp = match *self.timestamp_segment.lock().unwrap() {
+                    Some(ref segment) => running_time
+                        .and_then(|running_time| segment.position_from_running_time(running_time)),
+                    None => running_time,
+                };
+
+                // Same as GstBaseSrc: the buffers are presented at their capture time.
+                // If still referenced, the buffer is copied along with its flags
+                // (DISCONT, HEADER, DELTA_UNIT...) and metas, only the timestamps change.
+                let buffer = buffer.make_mut();
+                buffer.set_pts(timestamp);
+                buffer.set_dts(timestamp);
+
+                if let Some(ref reference_caps) = settings.reference_timestamp_caps {
+                    let reference_time = settings
+                        .reference_clock
+                        .as_ref()
+                        .map_or(now, |reference_clock| reference_clock.time());
+                    if let Some(reference_time) = reference_time {
+                        gst::ReferenceTimestampMeta::add(
+                            buffer,
+                            reference_caps,
+                            reference_time.saturating_sub(settings.capture_latency),
+                            gst::ClockTime::NONE,
+                        );
+                    }
+                }
+            } else {
+                gst::error!(CAT, imp: self, "Don't have a clock yet");
+                drop(settings);
+                drop(state);
+                self.error_detailed(
+                    ErrorCode::NoClock,
+                    "Don't have a clock to timestamp the buffer",
+                    None,
+                    Some(&buffer),
+                );
+                return Err(());
+            }
+        }
+        let compress_queue = settings.compress_queue;
+        drop(settings);
+        let pts = buffer.pts();
+
+        if compress_queue {
+            buffer = match compress::compress(buffer) {
+                Ok(buffer) => buffer,
+                Err(err) => {
+                    gst::error!(CAT, imp: self, "Failed to compress buffer: {}", err);
+                    return Err(());
+                }
+            };
+        }
+
+        let item = match (caps, deadline) {
+            (Some(caps), _) => StreamItem::CapsBuffer(caps, buffer),
+            (None, Some(deadline)) => StreamItem::DeadlineBuffer(deadline, buffer),
+            (None, None) => StreamItem::Buffer(buffer),
+        };
+
+        let (queued, was_empty, level) = {
+            // Keep the lock so that the task can't dequeue the buffer before its arrival is known
+            let mut arrivals = self.arrivals.lock().unwrap();
+            let was_empty = arrivals.queue.is_empty();
+            let queued = self.queue_item(item, "buffer");
+            if queued {
+                let now = Instant::now();
+                arrivals.queue.push_back(now);
+                arrivals.wake_fill();
+                // Only the accepted buffers account for the push intervals
+                self.stats.lock().unwrap().add_push(now);
+            }
+
+            (queued, was_empty, arrivals.queue.len())
+        };
+        drop(state);
+
+        if queued {
+            self.update_buffering(level);
+        }
+
+        if queued && was_empty && self.settings.lock().unwrap().emit_signals {
+            self.obj().emit_by_name::<()>("data-available", &[]);
+        }
+
+        if queued {
+            Ok(pts)
+        } else {
+            Err(())
+        }
+    }
+
+    /// Updates the buffering state for `level` queued buffers.
+    ///
+    /// Buffering stops once the level reaches the `high-watermark` and starts
+    /// again when it falls below the `low-watermark`. A buffering message is
+    /// posted for each transition and for each change of the level while buffering.
+    fn update_buffering(&self, level: usize) {
+        let (low_watermark, high_watermark) = {
+            let settings = self.settings.lock().unwrap();
+            if !settings.use_buffering {
+                return;
+            }
+            (settings.low_watermark, settings.high_watermark)
+        };
+        // max-buffers might have changed since the queue was created
+        let capacity = self.arrivals.lock().unwrap().capacity.max(1);
+
+        let percent = (level * 100 / capacity).min(100) as i32;
+        let ratio = level as f64 / capacity as f64;
+
+        let post_percent = {
+            let mut buffering = self.buffering.lock().unwrap();
+            let post_percent = if buffering.buffering {
+                if ratio >= high_watermark {
+                    gst::debug!(CAT, imp: self, "Reached high watermark, done buffering");
+                    buffering.buffering = false;
+                    Some(100)
+                } else {
+                    Some(percent).filter(|percent| buffering.percent != Some(*percent))
+                }
+            } else if ratio < low_watermark {
+                gst::debug!(CAT, imp: self, "Fell below low watermark, buffering");
+                buffering.buffering = true;
+                Some(percent)
+            } else {
+                None
+            };
+            if post_percent.is_some() {
+                buffering.percent = post_percent;
+            }
+
+            post_percent
+        };
+
+        if let Some(percent) = post_percent {
+            let _ = self.obj().post_message(
+                gst::message::Buffering::builder(percent)
+                    .src(&*self.obj())
+                    .build(),
+            );
+        }
+    }
+
+    /// Whether the queue is filling up, see `use-buffering`.
+    fn is_buffering(&self) -> bool {
+        self.settings.lock().unwrap().use_buffering && self.buffering.lock().unwrap().buffering
+    }
+
+    /// Records that the application queued an EOS, so no more buffers are expected.
+    fn eos_queued(&self) {
+        {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.eos_queued = true;
+            arrivals.wake_fill();
+        }
+        self.finish_buffering();
+    }
+
+    /// Ends buffering since no more data will be queued.
+    fn finish_buffering(&self) {
+        if !self.settings.lock().unwrap().use_buffering {
+            return;
+        }
+
+        let was_buffering = {
+            let mut buffering = self.buffering.lock().unwrap();
+            buffering.percent = Some(100);
+            std::mem::replace(&mut buffering.buffering, false)
+        };
+        if was_buffering {
+            gst::debug!(CAT, imp: self, "End of stream, done buffering");
+            let _ = self.obj().post_message(
+                gst::message::Buffering::builder(100)
+                    .src(&*self.obj())
+                    .build(),
+            );
+        }
+    }
+
+    /// Returns the time elapsed since the oldest queued buffer was pushed.
+    fn oldest_buffer_age(&self) -> gst::ClockTime {
+        self.arrivals
+            .lock()
+            .unwrap()
+            .queue
+            .front()
+            .map_or(gst::ClockTime::ZERO, |arrival| {
+                gst::ClockTime::from_nseconds(arrival.elapsed().as_nanos() as u64)
+            })
+    }
+
+    /// Returns the latency reported downstream.
+    ///
+    /// Buffers are timestamped `capture-latency` before they reach us. When it is
+    /// not set, the latency is derived from the framerate of the caps as the time
+    /// needed to fill the queue, i.e. `max-buffers` times the frame duration,
+    /// unless the stream is `sparse`.
+    /// Buffers are also held for the `reorder-window`.
+    fn latency(&self) -> gst::ClockTime {
+        let settings = self.settings.lock().unwrap();
+        let reorder_window = settings.reorder_window;
+        if settings.capture_latency != gst::ClockTime::ZERO {
+            return settings.capture_latency.saturating_add(reorder_window);
+        }
+        if settings.sparse {
+            // Sparse buffers don't fill the queue at the framerate
+            return reorder_window;
+        }
+
+        let caps = self
+            .configured_caps
+            .lock()
+            .unwrap()
+            .clone()
+            .or_else(|| settings.caps.clone());
+        let framerate = caps.as_ref().and_then(|caps| {
+            caps.structure(0)
+                .and_then(|s| s.get::<gst::Fraction>("framerate").ok())
+        });
+
+        let queue_latency = match framerate {
+            Some(framerate) if framerate.numer() > 0 && framerate.denom() > 0 => {
+                gst::ClockTime::SECOND
+                    .mul_div_floor(
+                        settings.max_buffers as u64 * framerate.denom() as u64,
+                        framerate.numer() as u64,
+                    )
+                    .unwrap_or(gst::ClockTime::ZERO)
+            }
+            _ => gst::ClockTime::ZERO,
+        };
+
+        queue_latency.saturating_add(reorder_window)
+    }
+
+    fn stats(&self) -> gst::Structure {
+        let mut stats = self.stats.lock().unwrap().to_structure();
+        stats.set("oldest-buffer-age", self.oldest_buffer_age().nseconds());
+
+        stats
+    }
+
+    /// Returns a snapshot of the internal state for diagnostics.
+    ///
+    /// Only takes short-lived locks, so it can be called in any state
+    /// without blocking the streaming task.
+    fn dump_state(&self) -> gst::Structure {
+        let obj = self.obj();
+
+        // Serialized, so that properties holding objects can be reported
+        let mut settings = gst::Structure::new_empty("settings");
+        for pspec in obj.list_properties().iter() {
+            if !pspec.flags().contains(glib::ParamFlags::READWRITE) {
+                continue;
+            }
+            if let Ok(value) = obj.property_value(pspec.name()).serialize() {
+                settings.set(pspec.name(), value.as_str());
+            }
+        }
+
+        let queued_buffers = self.arrivals.lock().unwrap().queue.len() as u32;
+        let spilling = self
+            .spill
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|spill| !spill.is_empty());
+
+        let mut state = gst::Structure::builder("application/x-ts-appsrc-state")
+            .field("element-state", format!("{:?}", obj.current_state()))
+            .field("task-state", format!("{:?}", self.task.state()))
+            .field("shutting-down", *self.shutting_down.lock().unwrap())
+            .field("throttled", *self.throttled.lock().unwrap())
+            .field("buffering", self.is_buffering())
+            .field("queued-buffers", queued_buffers)
+            .field("oldest-buffer-age", self.oldest_buffer_age().nseconds())
+            .field("spilling", spilling)
+            .field("buffer-count", *self.buffer_count.lock().unwrap())
+            .field("settings", settings)
+            .field("stats", self.stats.lock().unwrap().to_structure())
+            .build();
+        if let Some(caps) = self.configured_caps.lock().unwrap().clone() {
+            state.set("current-caps", caps);
+        }
+        if let Some(segment) = self.current_segment.lock().unwrap().clone() {
+            state.set("segment", segment);
+        }
+        if let Some(segment) = self.pending_segment.lock().unwrap().clone() {
+            state.set("pending-segment", segment);
+        }
+
+        state
+    }
+
+    /// Returns the running time according to a system `clock`.
+    ///
+    /// The first call after a (re)start aligns `clock` on the pipeline running time.
+    fn clock_source_running_time(
+        &self,
+        clock: &gst::Clock,
+        pipeline_running_time: gst::ClockTime,
+    ) -> Option<gst::ClockTime> {
+        let time = clock.time()?;
+        let offset = *self
+            .clock_source_offset
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| time.saturating_sub(pipeline_running_time));
+
+        time.checked_sub(offset)
+    }
+
+    fn flush_to_segment(&self, segment: gst::Segment) -> bool {
+        self.flush_to_segment_with(segment, None, || true)
+    }
+
+    /// Flushes to `segment`, calling `while_flushing` once the queued items are dropped.
+    ///
+    /// The segment is only applied if `while_flushing` returns `true`. The flush
+    /// and segment events carry `seqnum` if any, i.e. the seqnum of a seek.
+    fn flush_to_segment_with(
+        &self,
+        segment: gst::Segment,
+        seqnum: Option<gst::Seqnum>,
+        while_flushing: impl FnOnce() -> bool,
+    ) -> bool {
+        let format = self.settings.lock().unwrap().format;
+        if segment.format() != format {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Rejecting segment in format {:?}, configured format is {:?}",
+                segment.format(),
+                format,
+            );
+            return false;
+        }
+
+        if Context::is_context_thread() {
+            gst::error!(CAT, imp: self, "Can't flush from a Context thread");
+            return false;
+        }
+
+        let state = self.task.state();
+        if state != TaskState::Started && state != TaskState::Paused {
+            gst::debug!(CAT, imp: self, "Can't flush in state {:?}", state);
+            return false;
+        }
+
+        gst::debug!(CAT, imp: self, "Flushing to {:?}", segment);
+
+        let pad = self.src_pad.gst_pad();
+        let mut flush_start = gst::event::FlushStart::builder();
+        let mut flush_stop = gst::event::FlushStop::builder(true);
+        if let Some(seqnum) = seqnum {
+            flush_start = flush_start.seqnum(seqnum);
+            flush_stop = flush_stop.seqnum(seqnum);
+        }
+
+        pad.push_event(flush_start.build());
+        if let Err(err) = self.task.flush_start().block_on() {
+            gst::error!(CAT, imp: self, "Failed to start flush: {}", err);
+            return false;
+        }
+
+        let applied = while_flushing();
+        if applied {
+            *self.timestamp_segment.lock().unwrap() =
+                segment.clone().downcast::<gst::ClockTime>().ok();
+            *self.pending_segment.lock().unwrap() = Some(segment);
+            *self.pending_segment_seqnum.lock().unwrap() = seqnum;
+        }
+
+        pad.push_event(flush_stop.build());
+        if let Err(err) = self.task.flush_stop().block_on() {
+            gst::error!(CAT, imp: self, "Failed to stop flush: {}", err);
+            return false;
+        }
+
+        applied
+    }
+
+    /// Flushes and switches to `caps`, see the `switch-format` signal.
+    fn switch_format(&self, caps: gst::Caps) -> bool {
+        let format = {
+            let settings = self.settings.lock().unwrap();
+            if self.rejects_caps_change(settings.lock_caps, &caps) {
+                return false;
+            }
+            settings.format
+        };
+
+        gst::debug!(CAT, imp: self, "Switching format to {:?}", caps);
+
+        let mut segment = gst::Segment::new();
+        segment.reset_with_format(format);
+        self.flush_to_segment_with(segment, None, || {
+            let mut settings = self.settings.lock().unwrap();
+            settings.caps = Some(caps.clone());
+            // Same as setting the caps property: the initial events carry the
+            // caps if the stream hasn't started yet
+            if self.configured_caps.lock().unwrap().is_some() {
+                *self.renegotiated_caps.lock().unwrap() = Some(caps);
+            }
+            true
+        })
+    }
+
+    /// Handles byte seeks for random-access streams, see `StreamType`.
+    fn seek(&self, event: &gst::event::Seek) -> bool {
+        let (stream_type, format) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.stream_type, settings.format)
+        };
+        if stream_type != StreamType::RandomAccess {
+            gst::debug!(CAT, imp: self, "Not seekable");
+            return false;
+        }
+
+        let (rate, flags, start_type, start, stop_type, stop) = event.get();
+        // The queued data would have to be kept until the application is done seeking
+        if !flags.contains(gst::SeekFlags::FLUSH) {
+            gst::warning!(CAT, imp: self, "Rejecting non-flushing seek {:?}", event);
+            return false;
+        }
+        if start.format() != format || format != gst::Format::Bytes {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Rejecting seek in format {:?}, configured format is {:?}",
+                start.format(),
+                format,
+            );
+            return false;
+        }
+
+        let mut segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+        if segment
+            .do_seek(rate, flags, start_type, start, stop_type, stop)
+            .is_none()
+        {
+            gst::warning!(CAT, imp: self, "Invalid seek {:?}", event);
+            return false;
+        }
+        let offset = segment.start().map_or(0, |start| *start);
+
+        gst::debug!(CAT, imp: self, "Seeking to offset {}", offset);
+
+        self.flush_to_segment_with(segment.upcast(), Some(event.seqnum()), || {
+            let res = self.obj().emit_by_name::<bool>("seek-data", &[&offset]);
+            if !res {
+                gst::warning!(CAT, imp: self, "Application failed to seek to {}", offset);
+            }
+            res
+        })
+    }
+
+    /// Lets the application supply new caps upon a downstream reconfigure.
+    fn renegotiate(&self) {
+        if !self.settings.lock().unwrap().emit_signals {
+            return;
+        }
+
+        let current_caps = self.configured_caps.lock().unwrap().clone();
+        let caps = self
+            .obj()
+            .emit_by_name::<Option<gst::Caps>>("renegotiate", &[&current_caps]);
+        if let Some(caps) = caps {
+            gst::debug!(CAT, imp: self, "Renegotiating with {:?}", caps);
+            *self.renegotiated_caps.lock().unwrap() = Some(caps);
+        }
+    }
+
+    fn check_segment_format(&self, event: &gst::Event) -> bool {
+        if let gst::EventView::Segment(ev) = event.view() {
+            let format = self.settings.lock().unwrap().format;
+            let segment_format = ev.segment().format();
+            if segment_format != format {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Rejecting segment in format {:?}, configured format is {:?}",
+                    segment_format,
+                    format,
+                );
+                return false;
+            }
+        }
+
+        true
+    }
+
+    fn push_event(&self, event: gst::Event) -> bool {
+        if !self.check_segment_format(&event) {
+            return false;
+        }
+
+        self.queue_item(StreamItem::Event(event), "event")
+    }
+
+    fn push_event_at(&self, event: gst::Event, running_time: gst::ClockTime) -> bool {
+        if !event.is_downstream() || !event.is_serialized() || event.type_() == gst::EventType::Eos
+        {
+            gst::error!(
+                CAT,
+                imp: self,
+                "Rejecting {:?}: not a serialized downstream event or EOS",
+                event
+            );
+            return false;
+        }
+
+        if !self.check_segment_format(&event) {
+            return false;
+        }
+
+        self.queue_item(
+            StreamItem::ScheduledEvent(running_time, event),
+            "scheduled event",
+        )
+    }
+
+    fn push_events(&self, events: gst::Array) -> bool {
+        let mut batch: Vec<gst::Event> = Vec::with_capacity(events.len());
+        for value in events.iter() {
+            let Ok(event) = value.get::<gst::Event>() else {
+                gst::error!(CAT, imp: self, "Rejecting batch item {:?}: not an event", value);
+                return false;
+            };
+
+            if !event.is_downstream() || !event.is_serialized() {
+                gst::error!(
+                    CAT,
+                    imp: self,
+                    "Rejecting {:?}: not a serialized downstream event",
+                    event
+                );
+                return false;
+            }
+
+            if !self.check_segment_format(&event) {
+                return false;
+            }
+
+            // Nothing is pushed after an EOS
+            if batch
+                .last()
+                .is_some_and(|last| last.type_() == gst::EventType::Eos)
+            {
+                gst::error!(CAT, imp: self, "Rejecting batch: EOS is not the last event");
+                return false;
+            }
+
+            batch.push(event);
+        }
+
+        let ends_with_eos = batch
+            .last()
+            .is_some_and(|last| last.type_() == gst::EventType::Eos);
+        let queued = self.queue_item(StreamItem::Events(batch), "events");
+        if queued && ends_with_eos {
+            self.eos_queued();
+        }
+
+        queued
+    }
+
+    fn reset_stream(&self) -> bool {
+        self.queue_item(StreamItem::ResetStream, "stream reset")
+    }
+
+    fn negotiate(&self) -> bool {
+        if Context::is_context_thread() {
+            gst::error!(CAT, imp: self, "Can't wait for negotiation on a Context thread");
+            return false;
+        }
+
+        let (ack_tx, ack_rx) = mpsc::sync_channel(1);
+        {
+            let state = self.task.lock_state();
+            if *state != TaskState::Started {
+                gst::debug!(CAT, imp: self, "Queuing negotiation in state {:?}", *state);
+                return self.queue_item(StreamItem::Negotiate(ack_tx), "negotiation");
+            }
+
+            if !self.queue_item(StreamItem::Negotiate(ack_tx), "negotiation") {
+                return false;
+            }
+        }
+
+        // The ack is dropped if the item is flushed. The item stays queued if the task
+        // is paused or stalls (e.g. waiting for `min-fill`), so don't wait forever.
+        let deadline = Instant::now() + NEGOTIATE_TIMEOUT;
+        loop {
+            match ack_rx.recv_timeout(NEGOTIATE_STATE_CHECK_INTERVAL) {
+                Ok(negotiated) => return negotiated,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return false,
+                Err(mpsc::RecvTimeoutError::Timeout) => (),
+            }
+
+            let state = self.task.state();
+            if state != TaskState::Started {
+                gst::debug!(CAT, imp: self, "Task {:?} while waiting for negotiation", state);
+                return false;
+            }
+
+            if Instant::now() >= deadline {
+                gst::warning!(CAT, imp: self, "Timed out waiting for negotiation");
+                return false;
+            }
+        }
+    }
+
+    fn end_of_stream(&self) -> bool {
+        let queued = self.queue_item(StreamItem::Event(gst::event::Eos::new()), "EOS");
+        if queued {
+            self.eos_queued();
+        }
+
+        queued
+    }
+
+    fn end_of_stream_with_info(&self, info: gst::Structure) -> bool {
+        let events = vec![
+            gst::event::CustomDownstream::new(info),
+            gst::event::Eos::new(),
+        ];
+        let queued = self.queue_item(StreamItem::Events(events), "EOS with info");
+        if queued {
+            self.eos_queued();
+        }
+
+        queued
+    }
+
+    fn end_of_stream_immediate(&self) -> bool {
+        if Context::is_context_thread() {
+            gst::error!(CAT, imp: self, "Can't stop the task from a Context thread");
+            return false;
+        }
+
+        let state = self.task.state();
+        if state != TaskState::Started && state != TaskState::Paused {
+            gst::debug!(CAT, imp: self, "Can't push EOS in state {:?}", state);
+            return false;
+        }
+
+        // Stopping the task drops the queued items
+        gst::debug!(CAT, imp: self, "Dropping queued items and pushing EOS");
+        if let Err(err) = self.task.stop().block_on() {
+            gst::error!(CAT, imp: self, "Failed to stop task: {}", err);
+            return false;
+        }
+
+        self.src_pad.gst_pad().push_event(gst::event::Eos::new())
+    }
+
+    /// Queues an item for the task loop.
+    ///
+    /// Buffers, events, EOS and stream resets all go through this single FIFO
+    /// (the channel, or the spill once it has taken over), so items pushed in
+    /// a given order from the same thread are forwarded downstream in that order.
+    fn queue_item(&self, item: StreamItem, what: &str) -> bool {
+        let is_full = {
+            let mut spill = self.spill.lock().unwrap();
+            // Once spilling, keep spilling so as to preserve ordering
+            if let Some(spill) = spill.as_mut().filter(|spill| !spill.is_empty()) {
+                return self.spill_item(spill, item, what);
+            }
+
+            let mut sender = self.sender.lock().unwrap();
+            let sender = match sender.as_mut() {
+                Some(sender) => sender,
+                None => return false,
+            };
+
+            match sender.try_send(item) {
+                Ok(_) => return true,
+                Err(err) if err.is_full() && spill.is_some() => {
+                    return self.spill_item(spill.as_mut().unwrap(), err.into_inner(), what);
+                }
+                Err(err) => {
+                    gst::error!(CAT, imp: self, "Failed to queue {}: {}", what, err);
+                    err.is_full()
+                }
+            }
+        };
+
+        if is_full {
+            self.warn_full(what);
+        }
+
+        false
+    }
+
+    /// Posts a warning message for items dropped on a full queue, if enabled.
+    ///
+    /// Warnings are throttled, the message reports the items dropped since the previous one.
+    fn warn_full(&self, what: &str) {
+        if !self.settings.lock().unwrap().warn_on_full {
+            return;
+        }
+
+        let dropped = {
+            let mut full_drops = self.full_drops.lock().unwrap();
+            full_drops.dropped += 1;
+
+            let now = Instant::now();
+            if full_drops
+                .last_warning
+                .is_some_and(|last| now.duration_since(last) < FULL_WARNING_INTERVAL)
+            {
+                return;
+            }
+
+            full_drops.last_warning = Some(now);
+            std::mem::take(&mut full_drops.dropped)
+        };
+
+        gst::element_imp_warning!(
+            self,
+            gst::ResourceError::NoSpaceLeft,
+            ("Queue full, dropping data"),
+            ["Dropped {} item(s), last one: {}", dropped, what]
+        );
+    }
+
+    fn spill_item(&self, spill: &mut Spill<StreamItem>, item: StreamItem, what: &str) -> bool {
+        gst::log!(CAT, imp: self, "Spilling {}", what);
+
+        // The compression marker doesn't need to be preserved
+        let item = match item {
+            StreamItem::Buffer(buffer) => compress::decompress(buffer).map(StreamItem::Buffer),
+            StreamItem::CapsBuffer(caps, buffer) => {
+                compress::decompress(buffer).map(|buffer| StreamItem::CapsBuffer(caps, buffer))
+            }
+            item => Ok(item),
+        };
+
+        // Only the buffer data and fields are written to the file,
+        // so buffers with metas are kept in memory along with the other items
+        let has_metas = |buffer: &gst::Buffer| buffer.iter_meta::<gst::Meta>().next().is_some();
+        let res = match item {
+            Ok(StreamItem::Buffer(buffer)) if !has_metas(&buffer) => {
+                spill.push_buffer(None, &buffer)
+            }
+            Ok(StreamItem::CapsBuffer(caps, buffer)) if !has_metas(&buffer) => {
+                spill.push_buffer(Some(caps), &buffer)
+            }
+            Ok(item) => {
+                if spill.push_item(item).is_err() {
+                    gst::warning!(CAT, imp: self, "Spill holds too many items, dropping {}", what);
+                    self.warn_full(what);
+                    return false;
+                }
+                Ok(())
+            }
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = res {
+            gst::error!(CAT, imp: self, "Failed to spill {}: {}", what, err);
+            return false;
+        }
+
+        true
+    }
+
+    /// Sets the setting for `pspec` in `settings`.
+    ///
+    /// Only `settings` is modified, see `apply_setting` for the side effects
+    /// once the new settings are committed.
+    fn set_setting(
+        &self,
+        settings: &mut Settings,
+        value: &glib::Value,
+        pspec: &glib::ParamSpec,
+    ) -> Result<(), glib::BoolError> {
+        match pspec.name() {
+            "context" => {
+                settings.context = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .unwrap_or_else(|| DEFAULT_CONTEXT.into());
+            }
+            "context-wait" => {
+                settings.context_wait = Duration::from_millis(
+                    value.get::<u32>().expect("type checked upstream").into(),
+                );
+            }
+            "caps" => {
+                let caps = value
+                    .get::<Option<gst::Caps>>()
+                    .expect("type checked upstream");
+                if let Some(ref caps) = caps {
+                    if self.rejects_caps_change(settings.lock_caps, caps) {
+                        gst::element_imp_warning!(
+                            self,
+                            gst::CoreError::Negotiation,
+                            ("Caps are locked"),
+                            ["Ignoring caps property change to {:?}", caps]
+                        );
+                        return Err(glib::bool_error!("Caps are locked"));
+                    }
+                }
+                settings.caps = caps;
+            }
+            "max-buffers" => {
+                settings.max_buffers = value.get().expect("type checked upstream");
+            }
+            "do-timestamp" => {
+                settings.do_timestamp = value.get().expect("type checked upstream");
+            }
+            "format" => {
+                settings.format = value.get().expect("type checked upstream");
+            }
+            "capture-latency" => {
+                settings.capture_latency = value
+                    .get::<u64>()
+                    .expect("type checked upstream")
+                    .nseconds();
+            }
+            "post-stream-messages" => {
+                settings.post_stream_messages = value.get().expect("type checked upstream");
+            }
+            "flush-mode" => {
+                settings.flush_mode = value.get().expect("type checked upstream");
+            }
+            "reference-timestamp-caps" => {
+                settings.reference_timestamp_caps = value.get().expect("type checked upstream");
+            }
+            "reference-clock" => {
+                settings.reference_clock = value.get().expect("type checked upstream");
+            }
+            "clock-source" => {
+                settings.clock_source = value.get().expect("type checked upstream");
+            }
+            "do-timestamp-mode" => {
+                settings.do_timestamp_mode = value.get().expect("type checked upstream");
+            }
+            "not-linked-policy" => {
+                settings.not_linked_policy = value.get().expect("type checked upstream");
+            }
+            "send-flush-on-stop" => {
+                settings.send_flush_on_stop = value.get().expect("type checked upstream");
+            }
+            "caps-intersect-mode" => {
+                settings.caps_intersect_mode = value.get().expect("type checked upstream");
+            }
+            "persist-segment" => {
+                settings.persist_segment = value.get().expect("type checked upstream");
+            }
+            "merge-memory" => {
+                settings.merge_memory = value.get().expect("type checked upstream");
+            }
+            "max-buffer-size" => {
+                settings.max_buffer_size = value.get().expect("type checked upstream");
+            }
+            "emit-signals" => {
+                settings.emit_signals = value.get().expect("type checked upstream");
+            }
+            "spill-to-disk" => {
+                settings.spill_to_disk = value.get().expect("type checked upstream");
+            }
+            "max-retries" => {
+                settings.max_retries = value.get().expect("type checked upstream");
+            }
+            "retry-interval" => {
+                settings.retry_interval = value
+                    .get::<u64>()
+                    .expect("type checked upstream")
+                    .nseconds();
+            }
+            "retry-errors" => {
+                settings.retry_errors = value.get().expect("type checked upstream");
+            }
+            "auto-discont" => {
+                settings.auto_discont = value.get().expect("type checked upstream");
+            }
+            "num-buffers" => {
+                settings.num_buffers = value.get().expect("type checked upstream");
+            }
+            "warn-on-full" => {
+                settings.warn_on_full = value.get().expect("type checked upstream");
+            }
+            "is-live" => {
+                settings.is_live = value.get().expect("type checked upstream");
+            }
+            "clip-to-segment" => {
+                settings.clip_to_segment = value.get().expect("type checked upstream");
+            }
+            "split-size" => {
+                settings.split_size = value.get().expect("type checked upstream");
+            }
+            "lock-caps" => {
+                settings.lock_caps = value.get().expect("type checked upstream");
+            }
+            "queue-backend" => {
+                settings.queue_backend = value.get().expect("type checked upstream");
+            }
+            "default-caps" => {
+                settings.default_caps = value.get().expect("type checked upstream");
+            }
+            "reject-when-paused" => {
+                settings.reject_when_paused = value.get().expect("type checked upstream");
+            }
+            "stream-type" => {
+                settings.stream_type = value.get().expect("type checked upstream");
+            }
+            "compress-queue" => {
+                let compress_queue = value.get().expect("type checked upstream");
+                if cfg!(not(feature = "v1_20")) && compress_queue {
+                    return Err(glib::bool_error!(
+                        "compress-queue requires the v1_20 feature"
+                    ));
+                }
+                settings.compress_queue = compress_queue;
+            }
+            "location" => {
+                settings.location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            "blocksize" => {
+                settings.blocksize = value.get().expect("type checked upstream");
+            }
+            "drop-empty-buffers" => {
+                settings.drop_empty_buffers = value.get().expect("type checked upstream");
+            }
+            "max-bitrate" => {
+                settings.max_bitrate = value.get().expect("type checked upstream");
+            }
+            "strict-zero-copy" => {
+                settings.strict_zero_copy = value.get().expect("type checked upstream");
+            }
+            "use-buffering" => {
+                settings.use_buffering = value.get().expect("type checked upstream");
+            }
+            "low-watermark" => {
+                settings.low_watermark = value.get().expect("type checked upstream");
+            }
+            "high-watermark" => {
+                settings.high_watermark = value.get().expect("type checked upstream");
+            }
+            "min-fill" => {
+                settings.min_fill = value.get().expect("type checked upstream");
+            }
+            "min-fill-percent" => {
+                settings.min_fill_percent = value.get().expect("type checked upstream");
+            }
+            "sparse" => {
+                settings.sparse = value.get().expect("type checked upstream");
+            }
+            "passive" => {
+                settings.passive = value.get().expect("type checked upstream");
+            }
+            "stream-id-seed" => {
+                settings.stream_id_seed = value.get().expect("type checked upstream");
+            }
+            "max-buffer-age" => {
+                settings.max_buffer_age = value
+                    .get::<u64>()
+                    .expect("type checked upstream")
+                    .nseconds();
+            }
+            "stream-flags" => {
+                let stream_flags = value
+                    .get::<gst::StreamFlags>()
+                    .expect("type checked upstream");
+                if stream_flags.contains(gst::StreamFlags::SELECT | gst::StreamFlags::UNSELECT) {
+                    return Err(glib::bool_error!(
+                        "stream-flags {:?}: select and unselect are exclusive",
+                        stream_flags
+                    ));
+                }
+                settings.stream_flags = stream_flags;
+            }
+            "eos-debounce" => {
+                settings.eos_debounce = Duration::from_millis(
+                    value.get::<u32>().expect("type checked upstream").into(),
+                );
+            }
+            "reorder-window" => {
+                settings.reorder_window = value
+                    .get::<u64>()
+                    .expect("type checked upstream")
+                    .nseconds();
+            }
+            "do-stats" => {
+                settings.do_stats = value.get().expect("type checked upstream");
+            }
+            "fill-on-underrun" => {
+                settings.fill_on_underrun = value.get().expect("type checked upstream");
+            }
+            "filler-buffer" => {
+                settings.filler_buffer = value.get().expect("type checked upstream");
+            }
+            "spill-path" => {
+                settings.spill_path = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+                    .map(PathBuf::from);
+            }
+            _ => unimplemented!(),
+        }
+
+        Ok(())
+    }
+
+    /// Checks the combination of `settings`, e.g. after setting several of them.
+    fn check_settings(&self, settings: &Settings) -> Result<(), glib::BoolError> {
+        if settings.low_watermark > settings.high_watermark {
+            return Err(glib::bool_error!(
+                "low-watermark {} above high-watermark {}",
+                settings.low_watermark,
+                settings.high_watermark
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Applies the side effects of the committed setting `name`.
+    fn apply_setting(&self, settings: &Settings, name: &str) {
+        match name {
+            "caps" => {
+                // Already streaming: send the new caps before the next item
+                if self.configured_caps.lock().unwrap().is_some() {
+                    *self.renegotiated_caps.lock().unwrap() = settings.caps.clone();
+                }
+            }
+            "clock-source" => {
+                *self.clock_source_offset.lock().unwrap() = None;
+            }
+            "persist-segment" => {
+                if !settings.persist_segment {
+                    *self.persisted_running_time.lock().unwrap() = None;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Applies the settings from the fields of `config` at once.
+    ///
+    /// Fields are named after the properties. Nothing is applied
+    /// if a field or the resulting combination is invalid.
+    fn configure(&self, config: &gst::StructureRef) -> bool {
+        let obj = self.obj();
+
+        let mut values = Vec::with_capacity(config.n_fields() as usize);
+        for (name, value) in config.iter() {
+            // Inherited properties, e.g. the object name, are not settings
+            let pspec = match obj.find_property(name) {
+                Some(pspec)
+                    if pspec.owner_type() == super::AppSrc::static_type()
+                        && pspec.flags().contains(glib::ParamFlags::WRITABLE) =>
+                {
+                    pspec
+                }
+                _ => {
+                    gst::warning!(CAT, imp: self, "Can't configure unknown setting {}", name);
+                    return false;
+                }
+            };
+            if !value.type_().is_a(pspec.value_type()) {
+                gst::warning!(
+                    CAT,
+                    imp: self,
+                    "Invalid type {} for setting {}, expected {}",
+                    value.type_(),
+                    name,
+                    pspec.value_type(),
+                );
+                return false;
+            }
+            if !pspec.value_is_valid(value) {
+                gst::warning!(CAT, imp: self, "Invalid value {:?} for setting {}", value, name);
+                return false;
+            }
+
+            values.push((pspec, value));
+        }
+
+        let old_latency = self.latency();
+        let mut settings = self.settings.lock().unwrap();
+        let mut new_settings = settings.clone();
+        for (pspec, value) in values.iter() {
+            if let Err(err) = self.set_setting(&mut new_settings, value, pspec) {
+                gst::warning!(CAT, imp: self, "Rejecting {}: {}", pspec.name(), err);
+                return false;
+            }
+        }
+        if let Err(err) = self.check_settings(&new_settings) {
+            gst::warning!(CAT, imp: self, "Rejecting configuration: {}", err);
+            return false;
+        }
+
+        if new_settings.do_timestamp && new_settings.format != gst::Format::Time {
+            gst::warning!(
+                CAT,
+                imp: self,
+                "do-timestamp requires the Time format, got {:?}",
+                new_settings.format,
+            );
+            return false;
+        }
+
+        let was_live = settings.is_live;
+        let is_live = new_settings.is_live;
+        *settings = new_settings;
+        for (pspec, _) in values.iter() {
+            self.apply_setting(&settings, pspec.name());
+        }
+        drop(settings);
+
+        if is_live != was_live || self.latency() != old_latency {
+            self.post_latency_changed();
+        }
+
+        true
+    }
+
+    /// Lets the pipeline recompute the latency after `is-live` or the latency changed.
+    fn post_latency_changed(&self) {
+        gst::debug!(CAT, imp: self, "Latency changed, asking for a recomputation");
+        let obj = self.obj();
+        let _ = obj.post_message(gst::message::Latency::builder().src(&*obj).build());
+    }
+
+    fn prepare(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Preparing");
+
+        let settings = self.settings.lock().unwrap();
+        let provided_context = self.provided_context.lock().unwrap().clone();
+        let context = match provided_context {
+            Some(context) => {
+                gst::debug!(CAT, imp: self, "Using provided Context {}", context.name());
+                context
+            }
+            None => Context::acquire(&settings.context, settings.context_wait).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to acquire Context: {}", err]
+                )
+            })?,
+        };
+        let max_buffers = settings.max_buffers.try_into().map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["Invalid max-buffers: {}, {}", settings.max_buffers, err]
+            )
+        })?;
+        let queue_backend = settings.queue_backend;
+        if let Some(ref location) = settings.location {
+            File::open(location).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Could not open file {}: {}", location.display(), err]
+                )
+            })?;
+        }
+        let spill = if settings.spill_to_disk {
+            let dir = settings
+                .spill_path
+                .clone()
+                .unwrap_or_else(std::env::temp_dir);
+            // Items which can't be written to the file are bounded like the queue
+            let spill = Spill::new(&dir, max_buffers).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenWrite,
+                    ["Failed to create spill file in {}: {}", dir.display(), err]
+                )
+            })?;
+            Some(spill)
+        } else {
+            None
+        };
+        drop(settings);
+
+        *self.spill.lock().unwrap() = spill;
+
+        *self.configured_caps.lock().unwrap() = None;
+        *self.buffer_count.lock().unwrap() = 0;
+        *self.pending_segment.lock().unwrap() = None;
+        *self.pending_segment_seqnum.lock().unwrap() = None;
+        *self.timestamp_segment.lock().unwrap() = None;
+        *self.stats.lock().unwrap() = Stats::default();
+        *self.renegotiated_caps.lock().unwrap() = None;
+        *self.current_segment.lock().unwrap() = None;
+        *self.configured_latency.lock().unwrap() = gst::ClockTime::ZERO;
+        {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            arrivals.queue.clear();
+            arrivals.eos_queued = false;
+            arrivals.capacity = max_buffers;
+        }
+        *self.full_drops.lock().unwrap() = FullDrops::default();
+        *self.buffering.lock().unwrap() = Buffering::default();
+
+        let (sender, receiver) = queue::new(queue_backend, max_buffers);
+        *self.sender.lock().unwrap() = Some(sender);
+
+        *self.running_context.lock().unwrap() = Some(context.clone());
+
+        self.task
+            .prepare(AppSrcTask::new(self.obj().clone(), receiver), context)
+            .block_on()?;
+
+        gst::debug!(CAT, imp: self, "Prepared");
+
+        Ok(())
+    }
+
+    fn unprepare(&self) {
+        gst::debug!(CAT, imp: self, "Unpreparing");
+
+        *self.shutting_down.lock().unwrap() = true;
+        *self.sender.lock().unwrap() = None;
+        self.task.unprepare().block_on().unwrap();
+        *self.spill.lock().unwrap() = None;
+        *self.running_context.lock().unwrap() = None;
+
+        gst::debug!(CAT, imp: self, "Unprepared");
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Stopping");
+        *self.shutting_down.lock().unwrap() = true;
+        let file_reader = self.file_reader.lock().unwrap().take();
+        if let Some(file_reader) = file_reader {
+            file_reader.stop(self);
+        }
+        self.task.stop().block_on()?;
+        *self.throttled.lock().unwrap() = false;
+        *self.clock_source_offset.lock().unwrap() = None;
+        *self.pending_segment.lock().unwrap() = None;
+        *self.pending_segment_seqnum.lock().unwrap() = None;
+        gst::debug!(CAT, imp: self, "Stopped");
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Starting");
+        *self.shutting_down.lock().unwrap() = false;
+        if *self.throttled.lock().unwrap() {
+            gst::debug!(CAT, imp: self, "Throttled, keeping the task paused");
+            self.task.pause().block_on()?;
+        } else {
+            self.task.start().block_on()?;
+        }
+        self.start_file_reader()?;
+        gst::debug!(CAT, imp: self, "Started");
+        Ok(())
+    }
+
+    /// Starts reading the `location` file unless already reading or a buffer stream is set.
+    fn start_file_reader(&self) -> Result<(), gst::ErrorMessage> {
+        let mut file_reader = self.file_reader.lock().unwrap();
+        if file_reader.is_some() || self.buffer_stream.lock().unwrap().is_some() {
+            return Ok(());
+        }
+
+        let (location, blocksize) = {
+            let settings = self.settings.lock().unwrap();
+            match settings.location {
+                Some(ref location) => (location.clone(), settings.blocksize as usize),
+                None => return Ok(()),
+            }
+        };
+
+        gst::debug!(CAT, imp: self, "Reading {}", location.display());
+        *file_reader = Some(FileReader::spawn(&self.obj(), location, blocksize).map_err(
+            |err| {
+                gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to spawn the file reader: {}", err]
+                )
+            },
+        )?);
+
+        Ok(())
+    }
+
+    /// Queues an item read from `location` once the queue has room for it.
+    ///
+    /// The item is dropped if it is rejected nonetheless, e.g. by `max-buffer-size`.
+    /// Returns `false` if the reader is stopped.
+    fn queue_when_room(
+        &self,
+        stopped: &AtomicBool,
+        what: &str,
+        queue: impl FnOnce() -> bool,
+    ) -> bool {
+        {
+            let mut arrivals = self.arrivals.lock().unwrap();
+            loop {
+                if stopped.load(Ordering::SeqCst) {
+                    return false;
+                }
+                if arrivals.queue.len() < arrivals.capacity {
+                    break;
+                }
+
+                arrivals = self.level_cond.wait(arrivals).unwrap();
+            }
+        }
+
+        if !queue() {
+            gst::warning!(CAT, imp: self, "Dropping {} read from location", what);
+        }
+
+        true
+    }
+
+    /// Pauses the task while downstream is throttling, keeping the items queued.
+    fn set_throttled(&self, throttled: bool) -> bool {
+        {
+            let mut cur_throttled = self.throttled.lock().unwrap();
+            if *cur_throttled == throttled {
+                return true;
+            }
+            *cur_throttled = throttled;
+        }
+
+        let state = self.task.state();
+        if throttled {
+            gst::debug!(CAT, imp: self, "Throttled by downstream");
+            if state == TaskState::Started {
+                return self.task.pause().await_maybe_on_context().is_ok();
+            }
+        } else {
+            gst::debug!(CAT, imp: self, "No longer throttled by downstream");
+            let obj = self.obj();
+            let streaming = match obj.current_state() {
+                gst::State::Playing => true,
+                // Non-live sources also stream in PAUSED to preroll
+                gst::State::Paused => !self.settings.lock().unwrap().is_live,
+                _ => false,
+            };
+            if state == TaskState::Paused && streaming {
+                return self.task.start().await_maybe_on_context().is_ok();
+            }
+        }
+
+        true
+    }
+
+    fn pause(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp: self, "Pausing");
+        self.task.pause().block_on()?;
+        *self.clock_source_offset.lock().unwrap() = None;
+        gst::debug!(CAT, imp: self, "Paused");
+        Ok(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AppSrc {
+    const NAME: &'static str = "GstTsAppSrc";
+    type Type = super::AppSrc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        Self {
+            src_pad: PadSrc::new(
+                gst::Pad::from_template(&klass.pad_template("src").unwrap()),
+                AppSrcPadHandler,
+            ),
+            task: Task::default(),
+            sender: Default::default(),
+            configured_caps: Default::default(),
+            buffer_count: Default::default(),
+            clock_source_offset: Default::default(),
+            pending_segment: Default::default(),
+            pending_segment_seqnum: Default::default(),
+            timestamp_segment: Default::default(),
+            stats: Default::default(),
+            persisted_running_time: Default::default(),
+            renegotiated_caps: Default::default(),
+            configured_latency: Mutex::new(gst::ClockTime::ZERO),
+            current_segment: Default::default(),
+            spill: Default::default(),
+            arrivals: Default::default(),
+            level_cond: Default::default(),
+            provided_context: Default::default(),
+            running_context: Default::default(),
+            shutting_down: Default::default(),
+            throttled: Default::default(),
+            full_drops: Default::default(),
+            buffering: Default::default(),
+            buffer_stream: Default::default(),
+            file_reader: Default::default(),
+            settings: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for AppSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
+            vec![
+                glib::ParamSpecString::builder("context")
+                    .nick("Context")
+                    .blurb("Context name to share threads with (applied when going to READY)")
+                    .default_value(Some(DEFAULT_CONTEXT))
+                    .build(),
+                glib::ParamSpecUInt::builder("context-wait")
+                    .nick("Context Wait")
+                    .blurb(
+                        "Throttle poll loop to run at most once every this many ms \
+                        (increasing it at runtime throttles this element only)",
+                    )
+                    .maximum(1000)
+                    .default_value(DEFAULT_CONTEXT_WAIT.as_millis() as u32)
+                    .build(),
+                glib::ParamSpecUInt::builder("max-buffers")
+                    .nick("Max Buffers")
+                    .blurb("Maximum number of buffers to queue up (applied when going to READY)")
+                    .minimum(1)
+                    .default_value(DEFAULT_MAX_BUFFERS)
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
+                    .nick("Caps")
+                    .blurb("Caps to use, sent before the next item if changed while streaming")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("do-timestamp")
+                    .nick("Do Timestamp")
+                    .blurb("Set the PTS and DTS of buffers to the current running time on arrival")
+                    .default_value(DEFAULT_DO_TIMESTAMP)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("format", DEFAULT_FORMAT)
+                    .nick("Format")
+                    .blurb("The format of the segment events")
+                    .build(),
+                glib::ParamSpecUInt64::builder("capture-latency")
+                    .nick("Capture Latency")
+                    .blurb(
+                        "Delay in ns between capture and push, subtracted from do-timestamp \
+                        timestamps and reported as minimum latency",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_CAPTURE_LATENCY.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("post-stream-messages")
+                    .nick("Post Stream Messages")
+                    .blurb(
+                        "Post application messages carrying the stream-id \
+                        on stream-start and on EOS",
+                    )
+                    .default_value(DEFAULT_POST_STREAM_MESSAGES)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("flush-mode", DEFAULT_FLUSH_MODE)
+                    .nick("Flush Mode")
+                    .blurb("Whether a flush drops the queued items or keeps them")
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("reference-timestamp-caps")
+                    .nick("Reference Timestamp Caps")
+                    .blurb(
+                        "Caps of the reference timestamp meta to attach \
+                        when do-timestamp is enabled (e.g. timestamp/x-ntp)",
+                    )
+                    .build(),
+                glib::ParamSpecObject::builder::<gst::Clock>("reference-clock")
+                    .nick("Reference Clock")
+                    .blurb(
+                        "Clock providing the reference timestamps \
+                        (NULL = use the pipeline clock)",
+                    )
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("clock-source", DEFAULT_CLOCK_SOURCE)
+                    .nick("Clock Source")
+                    .blurb("Clock used to compute the running time when do-timestamp is enabled")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "do-timestamp-mode",
+                    DEFAULT_DO_TIMESTAMP_MODE,
+                )
+                .nick("Do Timestamp Mode")
+                .blurb("Which buffers to timestamp when do-timestamp is enabled")
+                .mutable_playing()
+                .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "not-linked-policy",
+                    DEFAULT_NOT_LINKED_POLICY,
+                )
+                .nick("Not Linked Policy")
+                .blurb("What to do when pushing while the src pad is not linked")
+                .build(),
+                glib::ParamSpecBoolean::builder("send-flush-on-stop")
+                    .nick("Send Flush On Stop")
+                    .blurb("Push flush-start and flush-stop downstream when stopping")
+                    .default_value(DEFAULT_SEND_FLUSH_ON_STOP)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "caps-intersect-mode",
+                    DEFAULT_CAPS_INTERSECT_MODE,
+                )
+                .nick("Caps Intersect Mode")
+                .blurb("How the configured caps are matched against the caps query filter")
+                .build(),
+                glib::ParamSpecBoolean::builder("persist-segment")
+                    .nick("Persist Segment")
+                    .blurb(
+                        "Continue the running time of the previous run \
+                        when restarting after a stop (time format only)",
+                    )
+                    .default_value(DEFAULT_PERSIST_SEGMENT)
+                    .build(),
+                glib::ParamSpecBoolean::builder("merge-memory")
+                    .nick("Merge Memory")
+                    .blurb("Merge the memories of buffers into a single one before pushing")
+                    .default_value(DEFAULT_MERGE_MEMORY)
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-buffer-size")
+                    .nick("Max Buffer Size")
+                    .blurb("Reject buffers larger than this many bytes (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_BUFFER_SIZE)
+                    .build(),
+                glib::ParamSpecBoolean::builder("emit-signals")
+                    .nick("Emit Signals")
+                    .blurb("Emit the observation signals, such as state-changed")
+                    .default_value(DEFAULT_EMIT_SIGNALS)
+                    .build(),
+                glib::ParamSpecBoolean::builder("spill-to-disk")
+                    .nick("Spill To Disk")
+                    .blurb(
+                        "Write the buffers to a file instead of rejecting them \
+                        when max-buffers are queued, buffers with metas and other \
+                        items are still limited to max-buffers (applied when going to READY)",
+                    )
+                    .default_value(DEFAULT_SPILL_TO_DISK)
+                    .build(),
+                glib::ParamSpecString::builder("spill-path")
+                    .nick("Spill Path")
+                    .blurb(
+                        "Directory for the spill file (NULL = temporary directory, \
+                        applied when going to READY)",
+                    )
+                    .build(),
+                glib::ParamSpecUInt::builder("max-retries")
+                    .nick("Max Retries")
+                    .blurb("Number of times pushing a buffer is retried upon retry-errors")
+                    .default_value(DEFAULT_MAX_RETRIES)
+                    .build(),
+                glib::ParamSpecUInt64::builder("retry-interval")
+                    .nick("Retry Interval")
+                    .blurb("Delay in ns before retrying to push a buffer")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_RETRY_INTERVAL.nseconds())
+                    .build(),
+                glib::ParamSpecFlags::builder::<RetryErrors>("retry-errors")
+                    .nick("Retry Errors")
+                    .blurb("Flow errors for which pushing a buffer is retried")
+                    .default_value(DEFAULT_RETRY_ERRORS)
+                    .build(),
+                glib::ParamSpecBoolean::builder("auto-discont")
+                    .nick("Auto Discont")
+                    .blurb("Mark the first buffer after a stream start or a flush as discont")
+                    .default_value(DEFAULT_AUTO_DISCONT)
+                    .build(),
+                glib::ParamSpecBoolean::builder("warn-on-full")
+                    .nick("Warn On Full")
+                    .blurb("Post a (throttled) warning message when dropping data on a full queue")
+                    .default_value(DEFAULT_WARN_ON_FULL)
+                    .build(),
+                glib::ParamSpecBoolean::builder("fill-on-underrun")
+                    .nick("Fill On Underrun")
+                    .blurb("Push the filler-buffer when no buffer is queued within its duration")
+                    .default_value(DEFAULT_FILL_ON_UNDERRUN)
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Buffer>("filler-buffer")
+                    .nick("Filler Buffer")
+                    .blurb(
+                        "Buffer with a duration (e.g. silence or black) pushed on underrun \
+                        with fill-on-underrun, timestamped after the last buffer pushed",
+                    )
+                    .build(),
+                glib::ParamSpecBoolean::builder("do-stats")
+                    .nick("Do Stats")
+                    .blurb("Measure the time buffers spend in the queue, reported by get-stats")
+                    .default_value(DEFAULT_DO_STATS)
+                    .build(),
+                glib::ParamSpecUInt64::builder("reorder-window")
+                    .nick("Reorder Window")
+                    .blurb(
+                        "Hold buffers for this duration in ns and forward them in PTS order, \
+                        dropping buffers arriving later (0 = disabled)",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_REORDER_WINDOW.nseconds())
+                    .build(),
+                glib::ParamSpecBoolean::builder("is-live")
+                    .nick("Is Live")
+                    .blurb(
+                        "Whether to act as a live source, only streaming in PLAYING \
+                        (applied on the next state change when changed at runtime)",
+                    )
+                    .default_value(DEFAULT_IS_LIVE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("clip-to-segment")
+                    .nick("Clip To Segment")
+                    .blurb(
+                        "Clip buffers to the current segment, dropping buffers entirely \
+                        outside of it",
+                    )
+                    .default_value(DEFAULT_CLIP_TO_SEGMENT)
+                    .build(),
+                glib::ParamSpecUInt::builder("split-size")
+                    .nick("Split Size")
+                    .blurb(
+                        "Split buffers larger than this size in bytes into several buffers \
+                        (0 = disabled)",
+                    )
+                    .default_value(DEFAULT_SPLIT_SIZE)
+                    .build(),
+                glib::ParamSpecBoolean::builder("lock-caps")
+                    .nick("Lock Caps")
+                    .blurb(
+                        "Reject caps changes once the first caps are sent downstream, \
+                        instead of renegotiating",
+                    )
+                    .default_value(DEFAULT_LOCK_CAPS)
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("queue-backend", DEFAULT_QUEUE_BACKEND)
+                    .nick("Queue Backend")
+                    .blurb("Implementation of the queue (applied when going to READY)")
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("default-caps")
+                    .nick("Default Caps")
+                    .blurb(
+                        "Caps answered to caps queries until caps are sent downstream, \
+                        unlike caps these are never sent (NULL = ANY)",
+                    )
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("reject-when-paused")
+                    .nick("Reject When Paused")
+                    .blurb(
+                        "Reject buffers pushed while PAUSED, instead of queuing them \
+                        until PLAYING",
+                    )
+                    .default_value(DEFAULT_REJECT_WHEN_PAUSED)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("stream-type", DEFAULT_STREAM_TYPE)
+                    .nick("Stream Type")
+                    .blurb("Whether byte seeks are handled by the application with seek-data")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("compress-queue")
+                    .nick("Compress Queue")
+                    .blurb(
+                        "Compress the data of queued buffers, trading CPU on both ends \
+                        of the queue for memory with highly compressible data \
+                        (requires the v1_20 feature)",
+                    )
+                    .default_value(DEFAULT_COMPRESS_QUEUE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecString::builder("location")
+                    .nick("Location")
+                    .blurb(
+                        "File to read and queue in blocksize chunks, followed by EOS, \
+                        when no buffer stream is set (for tests and demos)",
+                    )
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("blocksize")
+                    .nick("Block Size")
+                    .blurb("Size in bytes of the chunks read from location")
+                    .minimum(1)
+                    .default_value(DEFAULT_BLOCKSIZE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("drop-empty-buffers")
+                    .nick("Drop Empty Buffers")
+                    .blurb("Drop the pushed buffers without data instead of forwarding them")
+                    .default_value(DEFAULT_DROP_EMPTY_BUFFERS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-bitrate")
+                    .nick("Max Bitrate")
+                    .blurb(
+                        "Pace the buffers so that the output stays under this bitrate \
+                        in bits/s, allowing 100ms bursts (0 = unlimited)",
+                    )
+                    .default_value(DEFAULT_MAX_BITRATE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("strict-zero-copy")
+                    .nick("Strict Zero Copy")
+                    .blurb(
+                        "Skip the buffer changes which would require a copy because the buffer \
+                        is still referenced elsewhere, i.e. no auto-discont flag and no \
+                        clipping to the segment for such buffers",
+                    )
+                    .default_value(DEFAULT_STRICT_ZERO_COPY)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("use-buffering")
+                    .nick("Use Buffering")
+                    .blurb(
+                        "Post buffering messages depending on the queue level \
+                        relative to max-buffers",
+                    )
+                    .default_value(DEFAULT_USE_BUFFERING)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("low-watermark")
+                    .nick("Low Watermark")
+                    .blurb(
+                        "Start buffering when the queue level falls below this ratio \
+                        (ignored if above high-watermark)",
+                    )
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_LOW_WATERMARK)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("high-watermark")
+                    .nick("High Watermark")
+                    .blurb(
+                        "Stop buffering when the queue level reaches this ratio \
+                        (ignored if below low-watermark)",
+                    )
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_HIGH_WATERMARK)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("min-fill")
+                    .nick("Min Fill")
+                    .blurb(
+                        "Number of buffers to queue before pushing on start and after a flush \
+                        (0 = disabled, capped to max-buffers)",
+                    )
+                    .default_value(DEFAULT_MIN_FILL)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("min-fill-percent")
+                    .nick("Min Fill Percent")
+                    .blurb(
+                        "Percentage of max-buffers to queue before pushing on start and after \
+                        a flush, the greater of min-fill and this applies (0 = disabled)",
+                    )
+                    .maximum(100)
+                    .default_value(DEFAULT_MIN_FILL_PERCENT)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("sparse")
+                    .nick("Sparse")
+                    .blurb(
+                        "Sparse stream, e.g. subtitles: flag the stream as sparse, fill the \
+                        time between the buffers with gap events, don't timestamp the buffers \
+                        and don't report latency for the queue",
+                    )
+                    .default_value(DEFAULT_SPARSE)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("passive")
+                    .nick("Passive")
+                    .blurb(
+                        "Consume the buffers in the streaming task and account for them \
+                        without pushing them downstream, e.g. to measure the push path \
+                        in isolation (events are still pushed)",
+                    )
+                    .default_value(DEFAULT_PASSIVE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("stream-id-seed")
+                    .nick("Stream Id Seed")
+                    .blurb(
+                        "Seed of the generated stream ids, for reproducible ids \
+                        (0 = random, applied when going to READY)",
+                    )
+                    .default_value(DEFAULT_STREAM_ID_SEED)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-buffer-age")
+                    .nick("Max Buffer Age")
+                    .blurb(
+                        "Drop the buffers queued for longer than this in ns instead of \
+                        pushing them, except buffers with caps (0 = unlimited)",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_MAX_BUFFER_AGE.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecFlags::builder::<gst::StreamFlags>("stream-flags")
+                    .nick("Stream Flags")
+                    .blurb(
+                        "Flags of the stream-start events, e.g. sparse for mixer inputs \
+                        (select and unselect are exclusive)",
+                    )
+                    .default_value(DEFAULT_STREAM_FLAGS)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("eos-debounce")
+                    .nick("EOS Debounce")
+                    .blurb(
+                        "Hold EOS for this many ms before pushing it, canceling it upon \
+                        a flush, a stop or a stream reset (0 = push immediately)",
+                    )
+                    .default_value(DEFAULT_EOS_DEBOUNCE.as_millis() as u32)
+                    .build(),
+                glib::ParamSpecInt::builder("num-buffers")
+                    .nick("Num Buffers")
+                    .blurb(
+                        "Number of buffers to forward before sending EOS \
+                        (0 = EOS on the first buffer, -1 = unlimited)",
+                    )
+                    .minimum(-1)
+                    .default_value(DEFAULT_NUM_BUFFERS)
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Caps>("current-caps")
+                    .nick("Current Caps")
+                    .blurb("The caps sent downstream, NULL before the first caps event")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Segment>("segment")
+                    .nick("Segment")
+                    .blurb(
+                        "The segment sent downstream, a default segment in the configured \
+                        format before the first one",
+                    )
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoolean::builder("throttled")
+                    .nick("Throttled")
+                    .blurb("Whether pushing is paused because downstream signaled enough data")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecBoolean::builder("buffering")
+                    .nick("Buffering")
+                    .blurb("Whether the queue is filling up to the high watermark (use-buffering)")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("oldest-buffer-age")
+                    .nick("Oldest Buffer Age")
+                    .blurb("Time since the oldest queued buffer was pushed (ns)")
+                    .maximum(u64::MAX - 1)
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("latency")
+                    .nick("Latency")
+                    .blurb(
+                        "The reported latency: capture-latency if set, otherwise \
+                        max-buffers times the frame duration from the caps, \
+                        plus the reorder-window (ns)",
+                    )
+                    .maximum(u64::MAX - 1)
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("configured-latency")
+                    .nick("Configured Latency")
+                    .blurb("The latency configured by the pipeline in the last Latency event (ns)")
+                    .maximum(u64::MAX - 1)
+                    .read_only()
+                    .build(),
+                glib::ParamSpecUInt64::builder("buffer-count")
+                    .nick("Buffer Count")
+                    .blurb("Number of buffers pushed since last stream-start or flush")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
+            vec![
+                glib::subclass::Signal::builder("push-buffer")
+                    .param_types([gst::Buffer::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+
+                        Some(elem.imp().push_buffer(buffer).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-meta:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 * @structure: The fields to attach to @buffer
+                 *
+                 * Attaches the fields of @structure to @buffer using the
+                 * `GstTsAppSrcMeta` custom meta, then queues @buffer.
+                 *
+                 * Requires the `v1_20` feature, @buffer is rejected otherwise.
+                 *
+                 * Returns: %TRUE if the buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-buffer-meta")
+                    .param_types([gst::Buffer::static_type(), gst::Structure::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let structure = args[2].get::<gst::Structure>().expect("signal arg");
+
+                        Some(elem.imp().push_buffer_meta(buffer, structure).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-protected:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 * @info: The protection info, e.g. key id and IV
+                 *
+                 * Attaches a #GstProtectionMeta holding @info to @buffer for downstream
+                 * decryptors, then queues @buffer.
+                 *
+                 * Returns: %TRUE if the buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-buffer-protected")
+                    .param_types([gst::Buffer::static_type(), gst::Structure::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let info = args[2].get::<gst::Structure>().expect("signal arg");
+
+                        Some(elem.imp().push_buffer_protected(buffer, info).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-deadline:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 * @deadline: The running time after which @buffer is no longer relevant
+                 *
+                 * Queues @buffer, which is dropped instead of being forwarded if the
+                 * pipeline running time passed @deadline when it is dequeued, e.g.
+                 * because the queue was backed up. Drops are counted in the
+                 * `deadline-drops` statistics.
+                 *
+                 * Returns: %TRUE if the buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-buffer-deadline")
+                    .param_types([gst::Buffer::static_type(), u64::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let deadline = args[2].get::<u64>().expect("signal arg");
+
+                        Some(
+                            elem.imp()
+                                .push_buffer_deadline(buffer, deadline.nseconds())
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::compute-timestamp:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer being pushed
+                 * @running_time: The current running time, %GST_CLOCK_TIME_NONE
+                 * without a clock
+                 *
+                 * Emitted from the pushing thread for each buffer which could be
+                 * timestamped with #GstTsAppSrc:do-timestamp, if #GstTsAppSrc:emit-signals
+                 * is enabled and a handler is connected. Lets the application compute
+                 * the timestamps, e.g. from the content of @buffer. Emitting a signal
+                 * for each buffer is costly, so only connect a handler when needed.
+                 *
+                 * Returns: (nullable): a #GstStructure with the `pts` and `dts` to set,
+                 * a missing field unsets the timestamp. %NULL to fall back to
+                 * #GstTsAppSrc:do-timestamp, or keep the buffer timestamps if disabled.
+                 */
+                glib::subclass::Signal::builder("compute-timestamp")
+                    .param_types([gst::Buffer::static_type(), u64::static_type()])
+                    .return_type::<Option<gst::Structure>>()
+                    .build(),
+                /**
+                 * ts-appsrc::upstream-event:
+                 * @self: A ts-appsrc
+                 * @event: The upstream event
+                 *
+                 * Emitted from the thread sending @event for the upstream events
+                 * ts-appsrc doesn't handle itself, e.g. QoS, navigation, step, TOC select
+                 * or custom upstream events, if #GstTsAppSrc:emit-signals is enabled and
+                 * a handler is connected.
+                 *
+                 * Flushes, reconfigure, latency, seeks (see #GstTsAppSrc:stream-type) and
+                 * the throttle custom event are handled by the element and not emitted.
+                 *
+                 * Returns: %TRUE if the application handled @event, which is then
+                 * reported as handled upstream, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("upstream-event")
+                    .param_types([gst::Event::static_type()])
+                    .return_type::<bool>()
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-get-timestamp:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 *
+                 * Queues @buffer as #GstTsAppSrc::push-buffer does and returns its PTS,
+                 * which is the running time stamped on it when #GstTsAppSrc:do-timestamp
+                 * applies. Lets applications correlate their pushes with the timeline.
+                 *
+                 * Returns: the PTS of @buffer, %GST_CLOCK_TIME_NONE if it couldn't
+                 * be queued, was dropped by #GstTsAppSrc:drop-empty-buffers or has no PTS
+                 */
+                glib::subclass::Signal::builder("push-buffer-get-timestamp")
+                    .param_types([gst::Buffer::static_type()])
+                    .return_type::<u64>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+
+                        let pts = elem.imp().push_buffer_get_timestamp(buffer);
+                        Some(pts.map_or(u64::MAX, gst::ClockTime::nseconds).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-full-meta:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 * @pts: The PTS, %GST_CLOCK_TIME_NONE to keep the buffer's
+                 * @dts: The DTS, %GST_CLOCK_TIME_NONE to keep the buffer's
+                 * @duration: The duration, %GST_CLOCK_TIME_NONE to keep the buffer's
+                 * @offset: The offset, %GST_BUFFER_OFFSET_NONE to keep the buffer's
+                 * @offset_end: The offset end, %GST_BUFFER_OFFSET_NONE to keep the buffer's
+                 *
+                 * Sets the timing fields of @buffer and queues it.
+                 * #GstTsAppSrc:do-timestamp doesn't apply to @buffer.
+                 *
+                 * Returns: %TRUE if the buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-buffer-full-meta")
+                    .param_types([
+                        gst::Buffer::static_type(),
+                        u64::static_type(),
+                        u64::static_type(),
+                        u64::static_type(),
+                        u64::static_type(),
+                        u64::static_type(),
+                    ])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let pts = args[2].get::<u64>().expect("signal arg");
+                        let dts = args[3].get::<u64>().expect("signal arg");
+                        let duration = args[4].get::<u64>().expect("signal arg");
+                        let offset = args[5].get::<u64>().expect("signal arg");
+                        let offset_end = args[6].get::<u64>().expect("signal arg");
+
+                        let clock_time = |value: u64| {
+                            (value != gst::ffi::GST_CLOCK_TIME_NONE)
+                                .then(|| gst::ClockTime::from_nseconds(value))
+                        };
+
+                        let res = elem.imp().push_buffer_full_meta(
+                            buffer,
+                            clock_time(pts),
+                            clock_time(dts),
+                            clock_time(duration),
+                            offset,
+                            offset_end,
+                        );
+
+                        Some(res.to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-wait-level:
+                 * @self: A ts-appsrc
+                 * @buffer: The buffer to push
+                 * @level: The number of queued buffers to wait for
+                 *
+                 * Queues @buffer, then blocks until at most @level buffers are queued.
+                 * Must not be called from a Context thread.
+                 *
+                 * Returns: %TRUE if the level was reached, %FALSE if the buffer
+                 * couldn't be queued or if the element was flushed or stopped
+                 */
+                glib::subclass::Signal::builder("push-buffer-wait-level")
+                    .param_types([gst::Buffer::static_type(), u32::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let level = args[2].get::<u32>().expect("signal arg");
+
+                        Some(elem.imp().push_buffer_wait_level(buffer, level).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-bytes:
+                 * @self: A ts-appsrc
+                 * @bytes: The data to push
+                 *
+                 * Wraps @bytes into a buffer and queues it. The data are not copied:
+                 * the buffer holds a reference on @bytes, so they must not be modified.
+                 *
+                 * Returns: %TRUE if the buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-bytes")
+                    .param_types([glib::Bytes::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let bytes = args[1].get::<glib::Bytes>().expect("signal arg");
+
+                        Some(elem.imp().push_bytes(bytes).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-buffer-with-caps:
+                 * @self: A ts-appsrc
+                 * @caps: The new caps
+                 * @buffer: The first buffer using @caps
+                 *
+                 * Queues a caps event and @buffer as a single item so that
+                 * no other item can be pushed in between. Fails if @caps would change
+                 * the caps sent downstream while #GstTsAppSrc:lock-caps is enabled.
+                 *
+                 * Returns: %TRUE if the caps and buffer could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-buffer-with-caps")
+                    .param_types([gst::Caps::static_type(), gst::Buffer::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let caps = args[1].get::<gst::Caps>().expect("signal arg");
+                        let buffer = args[2].get::<gst::Buffer>().expect("signal arg");
+
+                        Some(elem.imp().push_buffer_with_caps(caps, buffer).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-event:
+                 * @self: A ts-appsrc
+                 * @event: The event to push
+                 *
+                 * Queues @event so that it is serialized with the queued buffers: an event
+                 * pushed after a buffer is always forwarded downstream after that buffer.
+                 * Segment events must use the configured #GstTsAppSrc:format.
+                 *
+                 * Returns: %TRUE if the event could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-event")
+                    .param_types([gst::Event::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let event = args[1].get::<gst::Event>().expect("signal arg");
+
+                        Some(elem.imp().push_event(event).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-event-at:
+                 * @self: A ts-appsrc
+                 * @event: A serialized downstream event, other than EOS
+                 * @running_time: The running time at which to push @event
+                 *
+                 * Queues @event like #GstTsAppSrc::push-event, but holds it in the task
+                 * until the stream reaches @running_time, i.e. until a buffer starting
+                 * at or after @running_time is pushed. Events scheduled for a running time
+                 * the stream already passed are pushed immediately with a warning.
+                 *
+                 * Scheduled events are discarded on flush and stop. Those still held at EOS
+                 * are pushed right before the EOS.
+                 *
+                 * Returns: %TRUE if the event could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-event-at")
+                    .param_types([gst::Event::static_type(), u64::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let event = args[1].get::<gst::Event>().expect("signal arg");
+                        let running_time = args[2].get::<u64>().expect("signal arg");
+                        if running_time == u64::MAX {
+                            gst::error!(CAT, obj: elem, "Rejecting {:?}: no running time", event);
+                            return Some(false.to_value());
+                        }
 
-#[glib::object_subclass]
-impl ObjectSubclass for AppSrc {
-    const NAME: &'static str = "GstTsAppSrc";
-    type Type = super::AppSrc;
-    type ParentType = gst::Element;
+                        Some(
+                            elem.imp()
+                                .push_event_at(event, gst::ClockTime::from_nseconds(running_time))
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::push-events:
+                 * @self: A ts-appsrc
+                 * @events: A #GstValueArray of serialized downstream events
+                 *
+                 * Queues @events as a single item so that they are pushed
+                 * consecutively, with no buffer in between. An EOS can only be
+                 * the last event of @events.
+                 *
+                 * Returns: %TRUE if the events could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("push-events")
+                    .param_types([gst::Array::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let events = args[1].get::<gst::Array>().expect("signal arg");
 
-    fn with_class(klass: &Self::Class) -> Self {
-        Self {
-            src_pad: PadSrc::new(
-                gst::Pad::from_template(&klass.pad_template("src").unwrap()),
-                AppSrcPadHandler,
-            ),
-            task: Task::default(),
-            sender: Default::default(),
-            configured_caps: Default::default(),
-            settings: Default::default(),
-        }
-    }
-}
+                        Some(elem.imp().push_events(events).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::reset-stream:
+                 * @self: A ts-appsrc
+                 *
+                 * Forgets the sticky events once the queued items are pushed, so that
+                 * a new stream-start, caps and segment precede the next buffer.
+                 * Contrary to a flush, queued items are kept.
+                 *
+                 * Returns: %TRUE if the reset could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("reset-stream")
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
 
-impl ObjectImpl for AppSrc {
-    fn properties() -> &'static [glib::ParamSpec] {
-        static PROPERTIES: Lazy<Vec<glib::ParamSpec>> = Lazy::new(|| {
-            vec![
-                glib::ParamSpecString::builder("context")
-                    .nick("Context")
-                    .blurb("Context name to share threads with")
-                    .default_value(Some(DEFAULT_CONTEXT))
+                        Some(elem.imp().reset_stream().to_value())
+                    })
                     .build(),
-                glib::ParamSpecUInt::builder("context-wait")
-                    .nick("Context Wait")
-                    .blurb("Throttle poll loop to run at most once every this many ms")
-                    .maximum(1000)
-                    .default_value(DEFAULT_CONTEXT_WAIT.as_millis() as u32)
+                /**
+                 * ts-appsrc::caps-changed:
+                 * @self: A ts-appsrc
+                 * @caps: The caps sent downstream
+                 *
+                 * Emitted when new caps are sent downstream, if #GstTsAppSrc:emit-signals
+                 * is enabled. Unfixed caps are fixated against downstream first, so @caps
+                 * are the caps actually chosen.
+                 */
+                glib::subclass::Signal::builder("caps-changed")
+                    .param_types([gst::Caps::static_type()])
                     .build(),
-                glib::ParamSpecUInt::builder("max-buffers")
-                    .nick("Max Buffers")
-                    .blurb("Maximum number of buffers to queue up")
-                    .minimum(1)
-                    .default_value(DEFAULT_MAX_BUFFERS)
+                /**
+                 * ts-appsrc::state-changed:
+                 * @self: A ts-appsrc
+                 * @old_state: The name of the previous task state
+                 * @new_state: The name of the new task state
+                 *
+                 * Emitted from the streaming thread when the streaming task
+                 * changes state, if #GstTsAppSrc:emit-signals is enabled.
+                 */
+                glib::subclass::Signal::builder("state-changed")
+                    .param_types([String::static_type(), String::static_type()])
                     .build(),
-                glib::ParamSpecBoxed::builder::<gst::Caps>("caps")
-                    .nick("Caps")
-                    .blurb("Caps to use")
+                /**
+                 * ts-appsrc::data-available:
+                 * @self: A ts-appsrc
+                 *
+                 * Emitted from the pushing thread when a buffer is queued while no
+                 * other buffers were queued, if #GstTsAppSrc:emit-signals is enabled.
+                 * It is not emitted again until the queue has been drained.
+                 */
+                glib::subclass::Signal::builder("data-available").build(),
+                /**
+                 * ts-appsrc::error-detailed:
+                 * @self: A ts-appsrc
+                 * @details: The `ts-appsrc-error` structure: `code`
+                 * (#GstTsAppSrcErrorCode), `message` and, when relevant, `caps`,
+                 * `buffer-size`, `buffer-pts` and `buffer-dts`
+                 *
+                 * Emitted upon a failure, from the streaming thread or from the
+                 * pushing thread, if #GstTsAppSrc:emit-signals is enabled. Complements
+                 * the error messages posted on the bus, allowing applications to
+                 * handle specific failures.
+                 */
+                glib::subclass::Signal::builder("error-detailed")
+                    .param_types([gst::Structure::static_type()])
                     .build(),
-                glib::ParamSpecBoolean::builder("do-timestamp")
-                    .nick("Do Timestamp")
-                    .blurb("Timestamp buffers with the current running time on arrival")
-                    .default_value(DEFAULT_DO_TIMESTAMP)
+                /**
+                 * ts-appsrc::allocation:
+                 * @self: A ts-appsrc
+                 * @pool: (nullable): The buffer pool proposed by downstream
+                 * @allocator: (nullable): The allocator proposed by downstream
+                 * @params: The allocation parameters: `size`, `min-buffers` and
+                 * `max-buffers` of the pool, `flags`, `align`, `prefix` and `padding`
+                 * of the allocator
+                 *
+                 * Emitted from the streaming thread once downstream has answered the
+                 * allocation query following new caps, if #GstTsAppSrc:emit-signals is
+                 * enabled. Allows allocating buffers suited to zero-copy pushing.
+                 */
+                glib::subclass::Signal::builder("allocation")
+                    .param_types([
+                        gst::BufferPool::static_type(),
+                        gst::Allocator::static_type(),
+                        gst::Structure::static_type(),
+                    ])
                     .build(),
-            ]
-        });
+                /**
+                 * ts-appsrc::renegotiate:
+                 * @self: A ts-appsrc
+                 * @caps: (nullable): The caps currently sent downstream
+                 *
+                 * Emitted when downstream requests a reconfiguration,
+                 * if #GstTsAppSrc:emit-signals is enabled.
+                 *
+                 * Returns: (nullable): the caps to send before the next buffer,
+                 * %NULL to keep the current caps
+                 */
+                glib::subclass::Signal::builder("renegotiate")
+                    .param_types([gst::Caps::static_type()])
+                    .return_type::<Option<gst::Caps>>()
+                    .build(),
+                /**
+                 * ts-appsrc::negotiate:
+                 * @self: A ts-appsrc
+                 *
+                 * Pushes the stream-start, caps and segment events without any buffer,
+                 * after the items already queued.
+                 *
+                 * When PLAYING, blocks until the events are pushed, at most 5 seconds
+                 * and only as long as the task keeps running. Otherwise the
+                 * negotiation is only queued.
+                 *
+                 * Returns: %TRUE if downstream accepted the caps (or if the negotiation
+                 * could be queued when not PLAYING), %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("negotiate")
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
 
-        PROPERTIES.as_ref()
-    }
+                        Some(elem.imp().negotiate().to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::flush-to-segment:
+                 * @self: A ts-appsrc
+                 * @segment: The segment to use after the flush
+                 *
+                 * Flushes downstream and the queued items, then announces @segment
+                 * before the next buffer. When do-timestamp is enabled, subsequent
+                 * buffers are timestamped relative to @segment.
+                 *
+                 * Returns: %TRUE if the flush succeeded, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("flush-to-segment")
+                    .param_types([gst::Segment::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let segment = args[1].get::<gst::Segment>().expect("signal arg");
 
-    fn signals() -> &'static [glib::subclass::Signal] {
-        static SIGNALS: Lazy<Vec<glib::subclass::Signal>> = Lazy::new(|| {
-            vec![
-                glib::subclass::Signal::builder("push-buffer")
-                    .param_types([gst::Buffer::static_type()])
+                        Some(elem.imp().flush_to_segment(segment).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::switch-format:
+                 * @self: A ts-appsrc
+                 * @caps: The caps of the buffers pushed after the switch
+                 *
+                 * Switches the stream to @caps with a clean break, in a single operation:
+                 * the queued items are dropped, @caps replace #GstTsAppSrc:caps and the
+                 * next buffer starts a new segment.
+                 *
+                 * Downstream sees a flush-start and a flush-stop event immediately. Then,
+                 * with the next buffer: a caps event with @caps (fixated if needed), a
+                 * segment event with a default segment in the configured
+                 * #GstTsAppSrc:format and the buffer flagged DISCONT
+                 * (see #GstTsAppSrc:auto-discont). The stream-start event is not repeated.
+                 *
+                 * Returns: %TRUE if the format was switched, %FALSE otherwise,
+                 * e.g. when #GstTsAppSrc:lock-caps rejects @caps
+                 */
+                glib::subclass::Signal::builder("switch-format")
+                    .param_types([gst::Caps::static_type()])
                     .return_type::<bool>()
                     .action()
                     .class_handler(|_, args| {
                         let elem = args[0].get::<super::AppSrc>().expect("signal arg");
-                        let buffer = args[1].get::<gst::Buffer>().expect("signal arg");
+                        let caps = args[1].get::<gst::Caps>().expect("signal arg");
 
-                        Some(elem.imp().push_buffer(buffer).to_value())
+                        Some(elem.imp().switch_format(caps).to_value())
                     })
                     .build(),
+                /**
+                 * ts-appsrc::seek-data:
+                 * @self: A ts-appsrc
+                 * @offset: The byte offset to push from
+                 *
+                 * Emitted upon a byte seek when #GstTsAppSrc:stream-type is
+                 * random-access, while the queued items are flushed. The buffers
+                 * pushed after this signal returns start at @offset in the new segment.
+                 *
+                 * Returns: %TRUE if the application could seek to @offset, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("seek-data")
+                    .param_types([u64::static_type()])
+                    .return_type::<bool>()
+                    .build(),
                 /**
                  * ts-appsrc::end-of-stream:
                  * @self: A ts-appsrc
@@ -508,6 +5074,130 @@ impl ObjectImpl for AppSrc {
                         Some(elem.imp().end_of_stream().to_value())
                     })
                     .build(),
+                /**
+                 * ts-appsrc::end-of-stream-with-info:
+                 * @self: A ts-appsrc
+                 * @info: The stream-final information
+                 *
+                 * Queues a custom downstream event holding @info immediately
+                 * followed by EOS. Both are pushed after the items queued before.
+                 *
+                 * Returns: %TRUE if the EOS could be queued, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("end-of-stream-with-info")
+                    .param_types([gst::Structure::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let info = args[1].get::<gst::Structure>().expect("signal arg");
+
+                        Some(elem.imp().end_of_stream_with_info(info).to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::end-of-stream-immediate:
+                 * @self: A ts-appsrc
+                 *
+                 * Stops the streaming task and pushes EOS right away.
+                 *
+                 * Unlike #GstTsAppSrc::end-of-stream, the queued items are dropped.
+                 * No further buffers are accepted until the element is restarted.
+                 *
+                 * Returns: %TRUE if EOS could be pushed, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("end-of-stream-immediate")
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+
+                        Some(elem.imp().end_of_stream_immediate().to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::get-stats:
+                 * @self: A ts-appsrc
+                 *
+                 * Returns the statistics of the streaming task:
+                 *
+                 * - `starved-iterations`: iterations which had to wait for an item.
+                 * - `ready-iterations`: iterations for which an item was ready.
+                 * - `starvation-ratio`: fraction of the iterations which were starved.
+                 * - `interval-count`: number of intervals between buffer pushes.
+                 * - `interval-min`, `interval-max`, `interval-mean` and `interval-stddev`:
+                 *   statistics of the intervals between buffer pushes in nanoseconds.
+                 * - `oldest-buffer-age`: time since the oldest queued buffer was pushed
+                 *   in nanoseconds, 0 if no buffers are queued.
+                 * - `dwell-count`, `dwell-min`, `dwell-max`, `dwell-mean`, `dwell-median`
+                 *   and `dwell-p99`: statistics of the time buffers spent in the queue, from
+                 *   their push to their dequeuing for forwarding, in nanoseconds. Computed
+                 *   on the latest 1000 buffers, only with #GstTsAppSrc:do-stats.
+                 * - `deadline-drops`: buffers pushed with `push-buffer-deadline` dropped
+                 *   because their deadline had passed.
+                 * - `age-drops`: buffers dropped because they were queued for longer
+                 *   than #GstTsAppSrc:max-buffer-age.
+                 *
+                 * Returns: a #GstStructure with the statistics
+                 */
+                glib::subclass::Signal::builder("get-stats")
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+
+                        Some(elem.imp().stats().to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::dump-state:
+                 * @self: A ts-appsrc
+                 *
+                 * Returns a snapshot of the internal state for diagnostics: element and
+                 * task states, queue level, current caps and segment, the readable and
+                 * writable properties in a `settings` structure and the statistics from
+                 * #GstTsAppSrc::get-stats in a `stats` structure.
+                 *
+                 * Safe to call in any state, the streaming task is not blocked.
+                 *
+                 * Returns: a #GstStructure with the state
+                 */
+                glib::subclass::Signal::builder("dump-state")
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+
+                        Some(elem.imp().dump_state().to_value())
+                    })
+                    .build(),
+                /**
+                 * ts-appsrc::configure:
+                 * @self: A ts-appsrc
+                 * @config: The settings to apply
+                 *
+                 * Applies the settings from the fields of @config at once, e.g.
+                 * `config, format=time, do-timestamp=true`. Fields are named after the
+                 * writable ts-appsrc properties, inherited ones such as `name` excluded,
+                 * and must hold valid values of the property types.
+                 *
+                 * Nothing is applied if a field is unknown, has an invalid type or an
+                 * out of range value, or if the resulting combination is invalid, e.g.
+                 * do-timestamp without the time format.
+                 *
+                 * Returns: %TRUE if the configuration was applied, %FALSE otherwise
+                 */
+                glib::subclass::Signal::builder("configure")
+                    .param_types([gst::Structure::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::AppSrc>().expect("signal arg");
+                        let config = args[1].get::<gst::Structure>().expect("signal arg");
+
+                        Some(elem.imp().configure(&config).to_value())
+                    })
+                    .build(),
             ]
         });
 
@@ -515,29 +5205,26 @@ impl ObjectImpl for AppSrc {
     }
 
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let old_latency = self.latency();
         let mut settings = self.settings.lock().unwrap();
-        match pspec.name() {
-            "context" => {
-                settings.context = value
-                    .get::<Option<String>>()
-                    .expect("type checked upstream")
-                    .unwrap_or_else(|| DEFAULT_CONTEXT.into());
-            }
-            "context-wait" => {
-                settings.context_wait = Duration::from_millis(
-                    value.get::<u32>().expect("type checked upstream").into(),
-                );
-            }
-            "caps" => {
-                settings.caps = value.get().expect("type checked upstream");
-            }
-            "max-buffers" => {
-                settings.max_buffers = value.get().expect("type checked upstream");
-            }
-            "do-timestamp" => {
-                settings.do_timestamp = value.get().expect("type checked upstream");
-            }
-            _ => unimplemented!(),
+        let mut new_settings = settings.clone();
+        let res = self
+            .set_setting(&mut new_settings, value, pspec)
+            .and_then(|_| self.check_settings(&new_settings));
+        if let Err(err) = res {
+            gst::warning!(CAT, imp: self, "Ignoring {}: {}", pspec.name(), err);
+            return;
+        }
+
+        let was_live = settings.is_live;
+        let is_live = new_settings.is_live;
+        *settings = new_settings;
+        self.apply_setting(&settings, pspec.name());
+        drop(settings);
+
+        // e.g. max-buffers, capture-latency or reorder-window changed
+        if is_live != was_live || self.latency() != old_latency {
+            self.post_latency_changed();
         }
     }
 
@@ -549,6 +5236,94 @@ impl ObjectImpl for AppSrc {
             "caps" => settings.caps.to_value(),
             "max-buffers" => settings.max_buffers.to_value(),
             "do-timestamp" => settings.do_timestamp.to_value(),
+            "format" => settings.format.to_value(),
+            "capture-latency" => settings.capture_latency.nseconds().to_value(),
+            "post-stream-messages" => settings.post_stream_messages.to_value(),
+            "flush-mode" => settings.flush_mode.to_value(),
+            "reference-timestamp-caps" => settings.reference_timestamp_caps.to_value(),
+            "reference-clock" => settings.reference_clock.to_value(),
+            "clock-source" => settings.clock_source.to_value(),
+            "do-timestamp-mode" => settings.do_timestamp_mode.to_value(),
+            "not-linked-policy" => settings.not_linked_policy.to_value(),
+            "send-flush-on-stop" => settings.send_flush_on_stop.to_value(),
+            "caps-intersect-mode" => settings.caps_intersect_mode.to_value(),
+            "persist-segment" => settings.persist_segment.to_value(),
+            "merge-memory" => settings.merge_memory.to_value(),
+            "max-buffer-size" => settings.max_buffer_size.to_value(),
+            "emit-signals" => settings.emit_signals.to_value(),
+            "spill-to-disk" => settings.spill_to_disk.to_value(),
+            "max-retries" => settings.max_retries.to_value(),
+            "retry-interval" => settings.retry_interval.nseconds().to_value(),
+            "retry-errors" => settings.retry_errors.to_value(),
+            "auto-discont" => settings.auto_discont.to_value(),
+            "num-buffers" => settings.num_buffers.to_value(),
+            "warn-on-full" => settings.warn_on_full.to_value(),
+            "reorder-window" => settings.reorder_window.nseconds().to_value(),
+            "is-live" => settings.is_live.to_value(),
+            "clip-to-segment" => settings.clip_to_segment.to_value(),
+            "split-size" => settings.split_size.to_value(),
+            "lock-caps" => settings.lock_caps.to_value(),
+            "queue-backend" => settings.queue_backend.to_value(),
+            "default-caps" => settings.default_caps.to_value(),
+            "reject-when-paused" => settings.reject_when_paused.to_value(),
+            "stream-type" => settings.stream_type.to_value(),
+            "compress-queue" => settings.compress_queue.to_value(),
+            "location" => settings
+                .location
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+                .to_value(),
+            "blocksize" => settings.blocksize.to_value(),
+            "drop-empty-buffers" => settings.drop_empty_buffers.to_value(),
+            "stream-flags" => settings.stream_flags.to_value(),
+            "max-bitrate" => settings.max_bitrate.to_value(),
+            "strict-zero-copy" => settings.strict_zero_copy.to_value(),
+            "use-buffering" => settings.use_buffering.to_value(),
+            "low-watermark" => settings.low_watermark.to_value(),
+            "high-watermark" => settings.high_watermark.to_value(),
+            "eos-debounce" => (settings.eos_debounce.as_millis() as u32).to_value(),
+            "do-stats" => settings.do_stats.to_value(),
+            "fill-on-underrun" => settings.fill_on_underrun.to_value(),
+            "filler-buffer" => settings.filler_buffer.to_value(),
+            "spill-path" => settings
+                .spill_path
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned())
+                .to_value(),
+            "current-caps" => self.configured_caps.lock().unwrap().to_value(),
+            "segment" => self
+                .current_segment
+                .lock()
+                .unwrap()
+                .clone()
+                .unwrap_or_else(|| {
+                    let mut segment = gst::Segment::new();
+                    segment.reset_with_format(settings.format);
+                    segment
+                })
+                .to_value(),
+            "throttled" => self.throttled.lock().unwrap().to_value(),
+            "min-fill" => settings.min_fill.to_value(),
+            "min-fill-percent" => settings.min_fill_percent.to_value(),
+            "max-buffer-age" => settings.max_buffer_age.nseconds().to_value(),
+            "sparse" => settings.sparse.to_value(),
+            "passive" => settings.passive.to_value(),
+            "stream-id-seed" => settings.stream_id_seed.to_value(),
+            "buffering" => {
+                (settings.use_buffering && self.buffering.lock().unwrap().buffering).to_value()
+            }
+            "oldest-buffer-age" => self.oldest_buffer_age().nseconds().to_value(),
+            "latency" => {
+                drop(settings);
+                self.latency().nseconds().to_value()
+            }
+            "configured-latency" => self
+                .configured_latency
+                .lock()
+                .unwrap()
+                .nseconds()
+                .to_value(),
+            "buffer-count" => self.buffer_count.lock().unwrap().to_value(),
             _ => unimplemented!(),
         }
     }
@@ -608,9 +5383,19 @@ impl ElementImpl for AppSrc {
                     gst::StateChangeError
                 })?;
             }
-            gst::StateChange::PlayingToPaused => {
+            gst::StateChange::PlayingToPaused if self.settings.lock().unwrap().is_live => {
                 self.pause().map_err(|_| gst::StateChangeError)?;
             }
+            gst::StateChange::PausedToReady => {
+                self.stop().map_err(|_| gst::StateChangeError)?;
+                // Once the task can't push anymore, but before the pad is deactivated
+                if self.settings.lock().unwrap().send_flush_on_stop {
+                    gst::debug!(CAT, imp: self, "Resetting downstream");
+                    let pad = self.src_pad.gst_pad();
+                    pad.push_event(gst::event::FlushStart::new());
+                    pad.push_event(gst::event::FlushStop::new(true));
+                }
+            }
             gst::StateChange::ReadyToNull => {
                 self.unprepare();
             }
@@ -619,19 +5404,21 @@ impl ElementImpl for AppSrc {
 
         let mut success = self.parent_change_state(transition)?;
 
+        // Live sources only stream in PLAYING, others also stream in PAUSED to preroll
+        let is_live = self.settings.lock().unwrap().is_live;
         match transition {
-            gst::StateChange::ReadyToPaused => {
+            gst::StateChange::ReadyToPaused if is_live => {
                 success = gst::StateChangeSuccess::NoPreroll;
             }
+            gst::StateChange::ReadyToPaused => {
+                self.start().map_err(|_| gst::StateChangeError)?;
+            }
             gst::StateChange::PausedToPlaying => {
                 self.start().map_err(|_| gst::StateChangeError)?;
             }
-            gst::StateChange::PlayingToPaused => {
+            gst::StateChange::PlayingToPaused if is_live => {
                 success = gst::StateChangeSuccess::NoPreroll;
             }
-            gst::StateChange::PausedToReady => {
-                self.stop().map_err(|_| gst::StateChangeError)?;
-            }
             _ => (),
         }
 
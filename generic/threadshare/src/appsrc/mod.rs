@@ -18,16 +18,316 @@
 //
 // SPDX-License-Identifier: LGPL-2.1-or-later
 
+use futures::prelude::*;
+
 use gst::glib;
 use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use crate::runtime::Context;
 
+mod compress;
 mod imp;
+mod queue;
+mod reorder;
+mod spill;
+
+/// Name of the custom meta attached by the `push-buffer-meta` signal.
+///
+/// Requires the `v1_20` feature.
+///
+/// Downstream elements can retrieve the fields using
+/// `gst::meta::CustomMeta::from_buffer(buffer, META_NAME)`.
+#[cfg(feature = "v1_20")]
+pub const META_NAME: &str = "GstTsAppSrcMeta";
+
+/// Name of the custom meta overriding the caps of a single buffer.
+///
+/// Attach it with `gst::meta::CustomMeta::add(buffer, CAPS_META_NAME)` and set
+/// the `caps` field of its structure. If these caps differ from the stream caps,
+/// they are sent before the buffer and the stream caps are sent again after it
+/// was pushed successfully. The meta is removed before pushing the buffer.
+/// Each of these caps events triggers a renegotiation downstream, so this is
+/// only suited to occasional buffers.
+///
+/// Requires the `v1_20` feature.
+#[cfg(feature = "v1_20")]
+pub const CAPS_META_NAME: &str = "GstTsAppSrcCapsMeta";
+
+/// Name of the custom upstream event throttling the source.
+///
+/// Downstream elements which have enough data send a `gst::event::CustomUpstream`
+/// with a structure of this name and a `throttled` boolean field. While throttled,
+/// the source stops pushing and keeps the items queued, so the application can
+/// keep pushing until the queue is full. Pushing resumes once an event with
+/// `throttled` set to `false` is received or the element goes to READY.
+pub const THROTTLE_EVENT_NAME: &str = "GstTsAppSrcThrottle";
+
+/// What happens to the queued items upon a flush.
+///
+/// `Cancel` guarantees that nothing queued before the flush reaches downstream.
+/// `Pause` preserves the queued items, which are pushed after the flush, starting
+/// with a new segment.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcFlushMode")]
+pub enum FlushMode {
+    #[enum_value(name = "Cancel: Drop the queued items", nick = "cancel")]
+    Cancel,
+    #[enum_value(
+        name = "Pause: Keep the queued items and push them after the flush",
+        nick = "pause"
+    )]
+    Pause,
+}
+
+/// The clock used by `do-timestamp`.
+///
+/// System clocks are aligned on the pipeline running time when timestamping
+/// the first buffer after the element starts, and then progress independently
+/// from the pipeline clock.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcClockSource")]
+pub enum ClockSource {
+    #[enum_value(name = "Pipeline: Use the pipeline clock", nick = "pipeline")]
+    Pipeline,
+    #[enum_value(name = "Monotonic: Use the system monotonic clock", nick = "monotonic")]
+    Monotonic,
+    #[enum_value(name = "Realtime: Use the system realtime clock", nick = "realtime")]
+    Realtime,
+}
+
+/// The implementation of the queue between the push functions and the task.
+///
+/// With both backends, the pushing threads are serialized by the element. `Ring`
+/// preallocates `max-buffers` slots and avoids the allocation per queued item
+/// of `Channel`. Compare both with the `ts-appsrc-queue-benchmark` example
+/// before switching, the gain depends on the push rate and the platform.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcQueueBackend")]
+pub enum QueueBackend {
+    #[enum_value(name = "Channel: Asynchronous channel", nick = "channel")]
+    Channel,
+    #[enum_value(name = "Ring: Preallocated ring buffer", nick = "ring")]
+    Ring,
+}
+
+/// Whether the application can reposition the stream.
+///
+/// ts-appsrc only operates in push mode. With `RandomAccess` and the `bytes`
+/// format, flushing seeks flush the queued items and emit `seek-data` with the
+/// offset the application should push from. Non-flushing seeks are rejected.
+/// The flush and segment events carry the seqnum of the seek.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcStreamType")]
+pub enum StreamType {
+    #[enum_value(name = "Stream: No seeking is supported", nick = "stream")]
+    Stream,
+    #[enum_value(
+        name = "Random access: Byte seeks are handled by the application",
+        nick = "random-access"
+    )]
+    RandomAccess,
+}
+
+/// Which buffers are timestamped when `do-timestamp` is enabled.
+///
+/// As with GstBaseSrc, `do-timestamp` sets both the PTS and the DTS to the running
+/// time. Unlike GstBaseSrc, which only timestamps buffers without a DTS, `Always`
+/// overrides the application timestamps; `IfMissing` is the closest to GstBaseSrc.
+/// The GstBaseSrc `typefind` and `blocksize` properties aren't provided since the
+/// application pushes complete buffers along with their caps.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcDoTimestampMode")]
+pub enum DoTimestampMode {
+    #[enum_value(
+        name = "Always: Timestamp all buffers, overriding their timestamps",
+        nick = "always"
+    )]
+    Always,
+    #[enum_value(
+        name = "If missing: Only timestamp buffers without PTS nor DTS",
+        nick = "if-missing"
+    )]
+    IfMissing,
+    #[enum_value(name = "Never: Don't timestamp buffers", nick = "never")]
+    Never,
+}
+
+/// What happens when an item can't be pushed because the src pad is not linked.
+///
+/// With `Pause`, the item is dropped and the task is paused until the pad is
+/// linked, as signalled by a reconfigure event. Items queued meanwhile are kept.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcNotLinkedPolicy")]
+pub enum NotLinkedPolicy {
+    #[enum_value(name = "Error: Post an error and stop streaming", nick = "error")]
+    Error,
+    #[enum_value(name = "Pause: Pause until the pad is linked", nick = "pause")]
+    Pause,
+    #[enum_value(name = "Drop: Drop the item and keep streaming", nick = "drop")]
+    Drop,
+}
+
+/// How the configured caps are matched against the filter of a caps query.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcCapsIntersectMode")]
+pub enum CapsIntersectMode {
+    #[enum_value(
+        name = "Zig-zag: Intersect alternating between both caps",
+        nick = "zig-zag"
+    )]
+    ZigZag,
+    #[enum_value(name = "First: Keep the order of the filter", nick = "first")]
+    First,
+    #[enum_value(
+        name = "Subset: Answer the configured caps only if they are a subset of the filter",
+        nick = "subset"
+    )]
+    Subset,
+}
+
+/// The failure reported by the `error-detailed` signal.
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTsAppSrcErrorCode")]
+pub enum ErrorCode {
+    #[enum_value(name = "Flow error: Pushing downstream failed", nick = "flow-error")]
+    FlowError,
+    #[enum_value(
+        name = "Not negotiated: No common caps with downstream",
+        nick = "not-negotiated"
+    )]
+    NotNegotiated,
+    #[enum_value(name = "No clock: No clock to timestamp the buffer", nick = "no-clock")]
+    NoClock,
+    #[enum_value(
+        name = "Spill failed: Failed to read the spill file",
+        nick = "spill-failed"
+    )]
+    SpillFailed,
+    #[enum_value(
+        name = "Channel closed: The internal channel closed while streaming",
+        nick = "channel-closed"
+    )]
+    ChannelClosed,
+}
+
+/// The flow errors for which pushing a buffer is retried.
+#[glib::flags(name = "GstTsAppSrcRetryErrors")]
+pub enum RetryErrors {
+    #[flags_value(name = "Generic error", nick = "error")]
+    ERROR = 0b0000_0001,
+    #[flags_value(name = "Not negotiated", nick = "not-negotiated")]
+    NOT_NEGOTIATED = 0b0000_0010,
+    #[flags_value(name = "Not linked", nick = "not-linked")]
+    NOT_LINKED = 0b0000_0100,
+    #[flags_value(name = "Not supported", nick = "not-supported")]
+    NOT_SUPPORTED = 0b0000_1000,
+}
+
+impl RetryErrors {
+    fn contains_flow_error(self, err: gst::FlowError) -> bool {
+        let flag = match err {
+            gst::FlowError::Error => RetryErrors::ERROR,
+            gst::FlowError::NotNegotiated => RetryErrors::NOT_NEGOTIATED,
+            gst::FlowError::NotLinked => RetryErrors::NOT_LINKED,
+            gst::FlowError::NotSupported => RetryErrors::NOT_SUPPORTED,
+            _ => return false,
+        };
+
+        self.contains(flag)
+    }
+}
 
 glib::wrapper! {
     pub struct AppSrc(ObjectSubclass<imp::AppSrc>) @extends gst::Element, gst::Object;
 }
 
+impl AppSrc {
+    /// Sets the `Context` to run on, instead of acquiring one by name.
+    ///
+    /// This allows co-locating elements deterministically. Takes effect the next
+    /// time the element is prepared, i.e. on the NULL to READY transition.
+    /// `None` reverts to the `context` and `context-wait` properties.
+    pub fn set_runtime_context(&self, context: Option<Context>) {
+        self.imp().set_provided_context(context);
+    }
+
+    /// Returns the `Context` the element runs on, if it is prepared.
+    pub fn runtime_context(&self) -> Option<Context> {
+        self.imp().running_context()
+    }
+
+    /// Flushes the element without sending flush events downstream.
+    ///
+    /// Queued items are dropped or kept depending on the `flush-mode` property,
+    /// the segment reverts to the default one, and the next buffer is preceded by
+    /// a new segment event and marked as discont. Unlike flushing with
+    /// `FlushStart` / `FlushStop` events, downstream elements keep their data,
+    /// so this suits applications which handle the downstream flush themselves.
+    ///
+    /// Can be called from any thread. On a `Context` thread, the flush completes
+    /// asynchronously.
+    pub fn flush(&self) -> Result<(), glib::BoolError> {
+        self.imp().flush()
+    }
+
+    /// Pulls buffers from a `Stream` instead of the `push-buffer` signals.
+    ///
+    /// `make_stream` is called to subscribe to the stream when the task starts.
+    /// A flush or a stop drops the subscription and `make_stream` is called again
+    /// when streaming resumes. Buffers are forwarded as they are produced, without
+    /// going through the queue, so `do-timestamp`, `max-buffers` and
+    /// `max-buffer-size` don't apply. Items pushed using the signals are still
+    /// handled. The end of the stream is handled as an EOS. Takes precedence
+    /// over the `location` property.
+    ///
+    /// Takes effect the next time the task starts or is flushed.
+    pub fn set_buffer_stream<F, S>(&self, make_stream: F)
+    where
+        F: Fn() -> S + Send + Sync + 'static,
+        S: Stream<Item = gst::Buffer> + Send + 'static,
+    {
+        self.imp()
+            .set_buffer_stream(Some(Box::new(move || make_stream().boxed())));
+    }
+
+    /// Reverts to pushing buffers using the `push-buffer` signals.
+    ///
+    /// Takes effect the next time the task starts or is flushed.
+    pub fn unset_buffer_stream(&self) {
+        self.imp().set_buffer_stream(None);
+    }
+}
+
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        FlushMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        ClockSource::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        DoTimestampMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        NotLinkedPolicy::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        CapsIntersectMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        RetryErrors::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        ErrorCode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        QueueBackend::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        StreamType::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    #[cfg(feature = "v1_20")]
+    {
+        gst::meta::CustomMeta::register(META_NAME, &[]);
+        gst::meta::CustomMeta::register(CAPS_META_NAME, &[]);
+        gst::meta::CustomMeta::register(compress::META_NAME, &[]);
+    }
+
     gst::Element::register(
         Some(plugin),
         "ts-appsrc",
@@ -0,0 +1,199 @@
+// Copyright (C) 2026 agent <agent@local>
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+use concurrent_queue::{ConcurrentQueue, PopError, PushError};
+use futures::channel::mpsc;
+use futures::prelude::*;
+use futures::task::AtomicWaker;
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll};
+
+use super::QueueBackend;
+
+/// Error returned by `ItemSender::try_send`, holding back the item.
+#[derive(Debug)]
+pub enum SendError<T> {
+    Full(T),
+    Disconnected(T),
+}
+
+impl<T> SendError<T> {
+    pub fn is_full(&self) -> bool {
+        matches!(self, SendError::Full(_))
+    }
+
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Full(item) | SendError::Disconnected(item) => item,
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SendError::Full(_) => f.write_str("queue is full"),
+            SendError::Disconnected(_) => f.write_str("queue is disconnected"),
+        }
+    }
+}
+
+/// Error returned by `ItemReceiver::try_recv` when no items are queued.
+#[derive(Debug)]
+pub struct Empty;
+
+/// The pushing side of the queue.
+pub trait ItemSender<T>: fmt::Debug + Send {
+    fn try_send(&mut self, item: T) -> Result<(), SendError<T>>;
+}
+
+/// The task side of the queue.
+///
+/// The `Stream` ends once the sender is dropped and the queued items are consumed.
+pub trait ItemReceiver<T>: Stream<Item = T> + fmt::Debug + Send + Unpin {
+    /// Returns `Ok(None)` if the sender is dropped and no items are left.
+    fn try_recv(&mut self) -> Result<Option<T>, Empty>;
+}
+
+/// Creates a queue holding up to `capacity` items, plus one for the sender.
+#[allow(clippy::type_complexity)]
+pub fn new<T: fmt::Debug + Send + 'static>(
+    backend: QueueBackend,
+    capacity: usize,
+) -> (Box<dyn ItemSender<T>>, Box<dyn ItemReceiver<T>>) {
+    match backend {
+        QueueBackend::Channel => {
+            let (sender, receiver) = mpsc::channel(capacity);
+            (Box::new(sender), Box::new(receiver))
+        }
+        QueueBackend::Ring => {
+            // Same capacity as the channel, which has one guaranteed slot per sender
+            let (sender, receiver) = ring(capacity + 1);
+            (Box::new(sender), Box::new(receiver))
+        }
+    }
+}
+
+impl<T: fmt::Debug + Send> ItemSender<T> for mpsc::Sender<T> {
+    fn try_send(&mut self, item: T) -> Result<(), SendError<T>> {
+        mpsc::Sender::try_send(self, item).map_err(|err| {
+            if err.is_full() {
+                SendError::Full(err.into_inner())
+            } else {
+                SendError::Disconnected(err.into_inner())
+            }
+        })
+    }
+}
+
+impl<T: fmt::Debug + Send> ItemReceiver<T> for mpsc::Receiver<T> {
+    fn try_recv(&mut self) -> Result<Option<T>, Empty> {
+        self.try_next().map_err(|_| Empty)
+    }
+}
+
+#[derive(Debug)]
+struct Ring<T> {
+    queue: ConcurrentQueue<T>,
+    waker: AtomicWaker,
+}
+
+/// A bounded ring buffer.
+///
+/// `ConcurrentQueue` supports several producers and consumers, but the element
+/// only uses one of each: pushing threads are serialized by the Mutex around the
+/// sender and the task is the only consumer. Unlike the channel, which allocates
+/// each queued item, the slots are allocated upfront.
+fn ring<T>(capacity: usize) -> (RingSender<T>, RingReceiver<T>) {
+    let ring = Arc::new(Ring {
+        queue: ConcurrentQueue::bounded(capacity),
+        waker: AtomicWaker::new(),
+    });
+
+    (RingSender(ring.clone()), RingReceiver(ring))
+}
+
+#[derive(Debug)]
+struct RingSender<T>(Arc<Ring<T>>);
+
+impl<T: fmt::Debug + Send> ItemSender<T> for RingSender<T> {
+    fn try_send(&mut self, item: T) -> Result<(), SendError<T>> {
+        match self.0.queue.push(item) {
+            Ok(()) => {
+                self.0.waker.wake();
+                Ok(())
+            }
+            Err(PushError::Full(item)) => Err(SendError::Full(item)),
+            Err(PushError::Closed(item)) => Err(SendError::Disconnected(item)),
+        }
+    }
+}
+
+impl<T> Drop for RingSender<T> {
+    fn drop(&mut self) {
+        // The receiver can still consume the queued items
+        self.0.queue.close();
+        self.0.waker.wake();
+    }
+}
+
+#[derive(Debug)]
+struct RingReceiver<T>(Arc<Ring<T>>);
+
+impl<T> RingReceiver<T> {
+    fn pop(&self) -> Poll<Option<T>> {
+        match self.0.queue.pop() {
+            Ok(item) => Poll::Ready(Some(item)),
+            Err(PopError::Closed) => Poll::Ready(None),
+            Err(PopError::Empty) => Poll::Pending,
+        }
+    }
+}
+
+impl<T> Stream for RingReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Option<T>> {
+        if let Poll::Ready(item) = self.pop() {
+            return Poll::Ready(item);
+        }
+
+        self.0.waker.register(cx.waker());
+        // An item might have been pushed before the waker was registered
+        self.pop()
+    }
+}
+
+impl<T: fmt::Debug + Send> ItemReceiver<T> for RingReceiver<T> {
+    fn try_recv(&mut self) -> Result<Option<T>, Empty> {
+        match self.pop() {
+            Poll::Ready(item) => Ok(item),
+            Poll::Pending => Err(Empty),
+        }
+    }
+}
+
+impl<T> Drop for RingReceiver<T> {
+    fn drop(&mut self) {
+        self.0.queue.close();
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (C) 2026 agent <agent@local>
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Items held for a reordering window.
+///
+/// Each item is held until the oldest held item has waited for the window,
+/// items are then released in PTS order. Items with the same PTS are
+/// released in arrival order.
+#[derive(Debug)]
+pub struct Reorder<T> {
+    held: BTreeMap<(gst::ClockTime, u64), (Instant, T)>,
+    seq: u64,
+    last_pts: Option<gst::ClockTime>,
+}
+
+impl<T> Default for Reorder<T> {
+    fn default() -> Self {
+        Reorder {
+            held: BTreeMap::new(),
+            seq: 0,
+            last_pts: None,
+        }
+    }
+}
+
+impl<T> Reorder<T> {
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Whether an item with `pts` arrives after an item with a greater PTS was released.
+    pub fn is_late(&self, pts: gst::ClockTime) -> bool {
+        self.last_pts.is_some_and(|last_pts| pts < last_pts)
+    }
+
+    pub fn hold(&mut self, pts: gst::ClockTime, item: T) {
+        self.held.insert((pts, self.seq), (Instant::now(), item));
+        self.seq += 1;
+    }
+
+    /// Returns the instant at which the next item is due.
+    pub fn next_deadline(&self, window: Duration) -> Option<Instant> {
+        self.held
+            .values()
+            .map(|(arrival, _)| *arrival)
+            .min()
+            .map(|arrival| arrival + window)
+    }
+
+    /// Releases the item with the lowest PTS if the window has elapsed.
+    pub fn pop_due(&mut self, window: Duration) -> Option<T> {
+        if self.next_deadline(window)? > Instant::now() {
+            return None;
+        }
+
+        let ((pts, _), (_, item)) = self.held.pop_first()?;
+        self.last_pts = Some(pts);
+
+        Some(item)
+    }
+
+    /// Releases all the held items in PTS order.
+    pub fn drain_into(&mut self, items: &mut VecDeque<T>) {
+        while let Some(((pts, _), (_, item))) = self.held.pop_first() {
+            self.last_pts = Some(pts);
+            items.push_back(item);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.held.clear();
+        self.last_pts = None;
+    }
+
+    /// Forgets the last released PTS, e.g. upon a new segment.
+    pub fn reset_last_pts(&mut self) {
+        self.last_pts = None;
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright (C) 2026 agent <agent@local>
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Library General Public
+// License as published by the Free Software Foundation; either
+// version 2 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Library General Public License for more details.
+//
+// You should have received a copy of the GNU Library General Public
+// License along with this library; if not, write to the
+// Free Software Foundation, Inc., 51 Franklin Street, Suite 500,
+// Boston, MA 02110-1335, USA.
+//
+// SPDX-License-Identifier: LGPL-2.1-or-later
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// A buffer whose data was written to the spill file.
+///
+/// Only the data and the fields needed to rebuild the buffer are kept,
+/// so buffers with metas must be pushed as items instead.
+#[derive(Debug)]
+struct SpilledBuffer {
+    position: u64,
+    size: usize,
+    pts: Option<gst::ClockTime>,
+    dts: Option<gst::ClockTime>,
+    duration: Option<gst::ClockTime>,
+    offset: u64,
+    offset_end: u64,
+    flags: gst::BufferFlags,
+}
+
+#[derive(Debug)]
+enum Entry<T> {
+    Buffer(Option<gst::Caps>, SpilledBuffer),
+    Item(T),
+}
+
+/// An item retrieved from the `Spill`.
+#[derive(Debug)]
+pub enum Unspilled<T> {
+    Buffer(Option<gst::Caps>, gst::Buffer),
+    Item(T),
+}
+
+/// Queue overflow backed by a file.
+///
+/// Buffer data are written to the file while other items are kept in memory,
+/// so that the overall ordering is preserved. At most `max_items` items
+/// are kept in memory at once.
+#[derive(Debug)]
+pub struct Spill<T> {
+    path: PathBuf,
+    file: File,
+    write_position: u64,
+    entries: VecDeque<Entry<T>>,
+    max_items: usize,
+    items: usize,
+}
+
+impl<T> Spill<T> {
+    /// Creates a new spill file in the `dir` directory.
+    pub fn new(dir: &Path, max_items: usize) -> io::Result<Self> {
+        let path = dir.join(format!(
+            "ts-appsrc-{}-{:08x}.spill",
+            std::process::id(),
+            rand::random::<u32>(),
+        ));
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        Ok(Spill {
+            path,
+            file,
+            write_position: 0,
+            entries: VecDeque::new(),
+            max_items,
+            items: 0,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push_buffer(&mut self, caps: Option<gst::Caps>, buffer: &gst::Buffer) -> io::Result<()> {
+        let map = buffer
+            .map_readable()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        self.file.seek(SeekFrom::Start(self.write_position))?;
+        self.file.write_all(map.as_slice())?;
+
+        let spilled = SpilledBuffer {
+            position: self.write_position,
+            size: map.size(),
+            pts: buffer.pts(),
+            dts: buffer.dts(),
+            duration: buffer.duration(),
+            offset: buffer.offset(),
+            offset_end: buffer.offset_end(),
+            flags: buffer.flags(),
+        };
+        self.write_position += map.size() as u64;
+        self.entries.push_back(Entry::Buffer(caps, spilled));
+
+        Ok(())
+    }
+
+    /// Keeps `item` in memory, or gives it back if `max_items` are already kept.
+    pub fn push_item(&mut self, item: T) -> Result<(), T> {
+        if self.items >= self.max_items {
+            return Err(item);
+        }
+
+        self.items += 1;
+        self.entries.push_back(Entry::Item(item));
+
+        Ok(())
+    }
+
+    pub fn pop(&mut self) -> Option<io::Result<Unspilled<T>>> {
+        let entry = self.entries.pop_front()?;
+        let res = match entry {
+            Entry::Buffer(caps, spilled) => self
+                .read_buffer(spilled)
+                .map(|buffer| Unspilled::Buffer(caps, buffer)),
+            Entry::Item(item) => {
+                self.items -= 1;
+                Ok(Unspilled::Item(item))
+            }
+        };
+
+        if self.entries.is_empty() {
+            if let Err(err) = self.truncate() {
+                return Some(Err(err));
+            }
+        }
+
+        Some(res)
+    }
+
+    pub fn clear(&mut self) -> io::Result<()> {
+        self.entries.clear();
+        self.items = 0;
+        self.truncate()
+    }
+
+    fn read_buffer(&mut self, spilled: SpilledBuffer) -> io::Result<gst::Buffer> {
+        let mut data = vec![0; spilled.size];
+        self.file.seek(SeekFrom::Start(spilled.position))?;
+        self.file.read_exact(&mut data)?;
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(spilled.pts);
+            buffer.set_dts(spilled.dts);
+            buffer.set_duration(spilled.duration);
+            buffer.set_offset(spilled.offset);
+            buffer.set_offset_end(spilled.offset_end);
+            buffer.set_flags(spilled.flags);
+        }
+
+        Ok(buffer)
+    }
+
+    fn truncate(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.write_position = 0;
+
+        Ok(())
+    }
+}
+
+impl<T> Drop for Spill<T> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
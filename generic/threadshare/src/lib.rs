@@ -16,7 +16,7 @@
 #[macro_use]
 pub mod runtime;
 
-mod appsrc;
+pub mod appsrc;
 mod audiotestsrc;
 pub mod dataqueue;
 mod inputselector;
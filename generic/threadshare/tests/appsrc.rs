@@ -236,3 +236,4081 @@ fn pause_flush() {
     let _ = h.pull().unwrap();
     assert!(h.try_pull().is_none());
 }
+
+#[test]
+fn push_buffer_with_caps() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-push-buffer-with-caps");
+    }
+
+    h.play();
+
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    {
+        let appsrc = h.element().unwrap();
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        assert!(
+            appsrc.emit_by_name::<bool>("push-buffer-with-caps", &[&new_caps, &gst::Buffer::new()])
+        );
+    }
+
+    for _ in 0..2 {
+        let _buffer = h.pull().unwrap();
+    }
+
+    let event = h.pull_event().unwrap();
+    assert_eq!(event.type_(), gst::EventType::StreamStart);
+
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+
+    let event = h.pull_event().unwrap();
+    assert_eq!(event.type_(), gst::EventType::Segment);
+
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(new_caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn push_event_segment_format() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("format", gst::Format::Time);
+        appsrc.set_property("context", "appsrc-push-event-segment-format");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // Segment in a format other than the configured one is rejected
+    let segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+    assert!(!appsrc.emit_by_name::<bool>("push-event", &[&gst::event::Segment::new(&segment)]));
+
+    // Segment in the configured format is accepted
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    assert!(appsrc.emit_by_name::<bool>("push-event", &[&gst::event::Segment::new(&segment)]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    let _ = h.pull().unwrap();
+
+    let event = h.pull_event().unwrap();
+    assert_eq!(event.type_(), gst::EventType::StreamStart);
+
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Segment(ev) => assert_eq!(ev.segment().format(), gst::Format::Time),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+}
+
+#[test]
+fn reset_stream() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-reset-stream");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert!(appsrc.emit_by_name::<bool>("reset-stream", &[]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+
+    let mut stream_ids = Vec::new();
+    for _ in 0..2 {
+        let event = h.pull_event().unwrap();
+        match event.view() {
+            gst::EventView::StreamStart(ev) => stream_ids.push(ev.stream_id().to_owned()),
+            _ => panic!("Unexpected event {event:?}"),
+        }
+
+        let event = h.pull_event().unwrap();
+        assert_eq!(event.type_(), gst::EventType::Caps);
+
+        let event = h.pull_event().unwrap();
+        assert_eq!(event.type_(), gst::EventType::Segment);
+    }
+
+    assert_ne!(stream_ids[0], stream_ids[1]);
+}
+
+#[test]
+fn post_stream_messages() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let bus = gst::Bus::new();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_bus(Some(&bus));
+        appsrc.set_property("post-stream-messages", true);
+        appsrc.set_property("context", "appsrc-post-stream-messages");
+    }
+
+    h.play();
+
+    {
+        let appsrc = h.element().unwrap();
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    }
+
+    let _ = h.pull().unwrap();
+
+    let event = h.pull_event().unwrap();
+    let stream_id = match event.view() {
+        gst::EventView::StreamStart(ev) => ev.stream_id().to_owned(),
+        _ => panic!("Unexpected event {event:?}"),
+    };
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    for name in ["ts-appsrc-stream-start", "ts-appsrc-eos"] {
+        let msg = bus.pop_filtered(&[gst::MessageType::Application]).unwrap();
+        let s = msg.structure().unwrap();
+        assert!(s.has_name(name));
+        assert_eq!(s.get::<&str>("stream-id").unwrap(), stream_id);
+    }
+}
+
+#[test]
+fn fixate_caps() {
+    init();
+
+    let mut h =
+        gst_check::Harness::new_parse("ts-appsrc name=src ! capsfilter caps=foo/bar,width=42");
+
+    let appsrc = h
+        .element()
+        .unwrap()
+        .downcast::<gst::Bin>()
+        .unwrap()
+        .by_name("src")
+        .unwrap();
+
+    let caps = gst::Caps::builder("foo/bar")
+        .field("width", gst::IntRange::new(1, 100))
+        .build();
+    appsrc.set_property("caps", &caps);
+    appsrc.set_property("emit-signals", true);
+    appsrc.set_property("context", "appsrc-fixate-caps");
+
+    let changed_caps = std::sync::Arc::new(std::sync::Mutex::new(None));
+    appsrc.connect("caps-changed", false, {
+        let changed_caps = changed_caps.clone();
+        move |args| {
+            *changed_caps.lock().unwrap() = Some(args[1].get::<gst::Caps>().unwrap());
+            None
+        }
+    });
+
+    h.play();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let expected_caps = gst::Caps::builder("foo/bar").field("width", 42i32).build();
+
+    let _ = h.pull_event().unwrap();
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(expected_caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+
+    assert_eq!(changed_caps.lock().unwrap().as_ref(), Some(&expected_caps));
+}
+
+#[test]
+fn buffer_count() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-buffer-count");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert_eq!(appsrc.property::<u64>("buffer-count"), 0);
+
+    for n_buffers in [2, 1] {
+        for _ in 0..n_buffers {
+            assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        }
+
+        // Once the marker is received, the preceding buffers are accounted for
+        let marker = gst::event::CustomDownstream::new(gst::Structure::new_empty("marker"));
+        assert!(appsrc.emit_by_name::<bool>("push-event", &[&marker]));
+
+        for _ in 0..n_buffers {
+            let _ = h.pull().unwrap();
+        }
+
+        loop {
+            let event = h.pull_event().unwrap();
+            if event.type_() == gst::EventType::CustomDownstream {
+                break;
+            }
+        }
+
+        assert_eq!(appsrc.property::<u64>("buffer-count"), n_buffers);
+
+        assert!(appsrc.emit_by_name::<bool>("reset-stream", &[]));
+    }
+}
+
+#[test]
+fn flush_mode_pause() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property_from_str("flush-mode", "pause");
+        appsrc.set_property("context", "appsrc-flush-mode-pause");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    appsrc
+        .change_state(gst::StateChange::PlayingToPaused)
+        .unwrap();
+
+    // Buffer is queued during Paused
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::from_slice(vec![1, 2])]));
+
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+
+    appsrc
+        .change_state(gst::StateChange::PausedToPlaying)
+        .unwrap();
+
+    // Buffer queued before the flush is kept
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.size(), 2);
+}
+
+#[test]
+fn negotiate() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-negotiate");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("negotiate", &[]));
+
+    let event = h.pull_event().unwrap();
+    assert_eq!(event.type_(), gst::EventType::StreamStart);
+
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+
+    let event = h.pull_event().unwrap();
+    assert_eq!(event.type_(), gst::EventType::Segment);
+
+    assert!(h.try_pull().is_none());
+}
+
+#[test]
+fn negotiate_paused() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("caps", gst::Caps::builder("foo/bar").build());
+    appsrc.set_property("context", "appsrc-negotiate-paused");
+    // The task holds the negotiation until the queue is filled
+    appsrc.set_property("min-fill", 2u32);
+
+    h.play();
+
+    let waiter = std::thread::spawn({
+        let appsrc = appsrc.clone();
+        move || appsrc.emit_by_name::<bool>("negotiate", &[])
+    });
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    appsrc.set_state(gst::State::Paused).unwrap();
+
+    // Doesn't wait for the task to be restarted
+    assert!(!waiter.join().unwrap());
+}
+
+#[test]
+fn reference_timestamp_meta() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let reference_caps = gst::Caps::builder("timestamp/x-test").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("reference-timestamp-caps", &reference_caps);
+        appsrc.set_property("context", "appsrc-reference-timestamp-meta");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().is_some());
+
+    let meta = buffer.meta::<gst::ReferenceTimestampMeta>().unwrap();
+    assert_eq!(meta.reference(), reference_caps.as_ref());
+}
+
+#[test]
+fn segment_done() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-segment-done");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_flags(gst::SegmentFlags::SEGMENT);
+    segment.set_stop(gst::ClockTime::from_mseconds(100));
+    assert!(appsrc.emit_by_name::<bool>("push-event", &[&gst::event::Segment::new(&segment)]));
+
+    for pts in [0, 50] {
+        let mut buffer = gst::Buffer::new();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(pts));
+            buffer.set_duration(gst::ClockTime::from_mseconds(50));
+        }
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    }
+
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if let gst::EventView::SegmentDone(ev) = event.view() {
+            assert_eq!(
+                ev.get(),
+                gst::GenericFormattedValue::from(gst::ClockTime::from_mseconds(100))
+            );
+            break;
+        }
+    }
+}
+
+#[test]
+fn clock_source_monotonic() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property_from_str("clock-source", "monotonic");
+        appsrc.set_property("context", "appsrc-clock-source-monotonic");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    for _ in 0..2 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    let first = h.pull().unwrap().dts().unwrap();
+    let second = h.pull().unwrap().dts().unwrap();
+
+    // Progresses according to the monotonic clock, regardless of the pipeline clock
+    assert!(second >= first + gst::ClockTime::from_mseconds(10));
+}
+
+#[test]
+fn flush_to_segment() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-flush-to-segment");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(gst::ClockTime::from_seconds(10));
+    segment.set_base(gst::ClockTime::ZERO);
+    assert!(appsrc.emit_by_name::<bool>("flush-to-segment", &[&segment.upcast_ref().clone()]));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    let mut flush_start = false;
+    let mut flush_stop = false;
+    loop {
+        let event = h.pull_event().unwrap();
+        match event.view() {
+            gst::EventView::FlushStart(_) => flush_start = true,
+            gst::EventView::FlushStop(_) => {
+                assert!(flush_start);
+                flush_stop = true;
+            }
+            gst::EventView::Segment(ev) if flush_stop => {
+                assert_eq!(ev.segment(), segment.upcast_ref());
+                break;
+            }
+            _ => (),
+        }
+    }
+
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().unwrap() >= gst::ClockTime::from_seconds(10));
+
+    // A regular flush drops the flush-to-segment timestamping
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().unwrap() < gst::ClockTime::from_seconds(10));
+}
+
+#[test]
+fn not_linked_policy() {
+    init();
+
+    for (policy, task_state) in [
+        (None, "Paused"),
+        (Some("error"), "Error"),
+        (Some("pause"), "Paused"),
+        (Some("drop"), "Started"),
+    ] {
+        let appsrc = gst::ElementFactory::make("ts-appsrc")
+            .property(
+                "context",
+                format!("appsrc-not-linked-{}", policy.unwrap_or("default")),
+            )
+            .build()
+            .unwrap();
+        if let Some(policy) = policy {
+            appsrc.set_property_from_str("not-linked-policy", policy);
+        }
+
+        appsrc.set_state(gst::State::Playing).unwrap();
+
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+        // Wait for the buffer to be handled
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        loop {
+            let state = appsrc.emit_by_name::<gst::Structure>("dump-state", &[]);
+            if state.get::<u32>("queued-buffers").unwrap() == 0
+                && state.get::<&str>("task-state").unwrap() == task_state
+            {
+                break;
+            }
+            assert!(
+                std::time::Instant::now() < deadline,
+                "{policy:?}: {state:?}"
+            );
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        appsrc.set_state(gst::State::Null).unwrap();
+    }
+}
+
+#[test]
+#[cfg(feature = "v1_20")]
+fn push_buffer_meta() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-push-buffer-meta");
+    }
+
+    h.play();
+
+    let structure = gst::Structure::builder("analytics")
+        .field("label", "cat")
+        .field("score", 42i32)
+        .build();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-meta", &[&gst::Buffer::new(), &structure]));
+
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().is_some());
+
+    let meta = gst::meta::CustomMeta::from_buffer(&buffer, "GstTsAppSrcMeta").unwrap();
+    let s = meta.structure();
+    assert_eq!(s.get::<&str>("label").unwrap(), "cat");
+    assert_eq!(s.get::<i32>("score").unwrap(), 42);
+}
+
+#[test]
+fn send_flush_on_stop() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("send-flush-on-stop", true);
+        appsrc.set_property("context", "appsrc-send-flush-on-stop");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    appsrc.set_state(gst::State::Ready).unwrap();
+
+    let mut flush_start = false;
+    loop {
+        let event = h.pull_event().unwrap();
+        match event.view() {
+            gst::EventView::FlushStart(_) => flush_start = true,
+            gst::EventView::FlushStop(_) => {
+                assert!(flush_start);
+                break;
+            }
+            _ => (),
+        }
+    }
+    // The task is stopped before downstream is reset
+    assert!(h.try_pull_event().is_none());
+}
+
+#[test]
+fn starvation_stats() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-starvation-stats");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    for _ in 0..2 {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        let _ = h.pull().unwrap();
+    }
+
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    // The task waited for each buffer
+    assert!(stats.get::<u64>("starved-iterations").unwrap() >= 2);
+    let ratio = stats.get::<f64>("starvation-ratio").unwrap();
+    assert!(ratio > 0.0 && ratio <= 1.0);
+}
+
+#[test]
+fn caps_intersect_mode_subset() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").field("width", 42i32).build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property_from_str("caps-intersect-mode", "subset");
+        appsrc.set_property("context", "appsrc-caps-intersect-mode-subset");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let src_pad = appsrc.static_pad("src").unwrap();
+
+    let filter = gst::Caps::builder("foo/bar").build();
+    assert_eq!(src_pad.query_caps(Some(&filter)), caps);
+
+    let filter = gst::Caps::builder("foo/bar").field("width", 43i32).build();
+    assert!(src_pad.query_caps(Some(&filter)).is_empty());
+}
+
+#[test]
+fn persist_segment() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("persist-segment", true);
+        appsrc.set_property("context", "appsrc-persist-segment");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let mut buffer = gst::Buffer::new();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::ZERO);
+        buffer.set_duration(gst::ClockTime::SECOND);
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let _ = h.pull().unwrap();
+
+    appsrc.set_state(gst::State::Null).unwrap();
+    h.play();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let _ = h.pull().unwrap();
+
+    // A flush restarts the timeline
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let _ = h.pull().unwrap();
+
+    // The first segment is the one of the first run
+    let mut bases = Vec::new();
+    while bases.len() < 3 {
+        let event = h.pull_event().unwrap();
+        if let gst::EventView::Segment(ev) = event.view() {
+            let segment = ev.segment().downcast_ref::<gst::ClockTime>().unwrap();
+            bases.push(segment.base());
+        }
+    }
+    assert_eq!(
+        bases[1..],
+        [Some(gst::ClockTime::SECOND), Some(gst::ClockTime::ZERO)]
+    );
+}
+
+#[test]
+fn push_events() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-push-events");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let events = gst::Array::from_values([
+        gst::event::Tag::new(gst::TagList::new()).to_send_value(),
+        gst::event::CustomDownstream::new(gst::Structure::new_empty("custom")).to_send_value(),
+    ]);
+    assert!(appsrc.emit_by_name::<bool>("push-events", &[&events]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    // Upstream events can't be part of a batch
+    let events = gst::Array::from_values([gst::event::Reconfigure::new().to_send_value()]);
+    assert!(!appsrc.emit_by_name::<bool>("push-events", &[&events]));
+
+    // Events following an EOS would be dropped
+    let events = gst::Array::from_values([
+        gst::event::Eos::new().to_send_value(),
+        gst::event::Tag::new(gst::TagList::new()).to_send_value(),
+    ]);
+    assert!(!appsrc.emit_by_name::<bool>("push-events", &[&events]));
+
+    let mut events = vec![];
+    while let Some(event) = h.try_pull_event() {
+        events.push(event.type_());
+    }
+    let tag_idx = events
+        .iter()
+        .position(|t| *t == gst::EventType::Tag)
+        .unwrap();
+    assert_eq!(events[tag_idx + 1], gst::EventType::CustomDownstream);
+}
+
+#[test]
+fn current_caps() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-current-caps");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc
+        .property::<Option<gst::Caps>>("current-caps")
+        .is_none());
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("current-caps"),
+        Some(caps)
+    );
+}
+
+#[test]
+fn merge_memory() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("merge-memory", true);
+        appsrc.set_property("context", "appsrc-merge-memory");
+    }
+
+    h.play();
+
+    let mut buffer = gst::Buffer::new();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.append_memory(gst::Memory::from_slice([1u8, 2]));
+        buffer.append_memory(gst::Memory::from_slice([3u8, 4]));
+    }
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.n_memory(), 1);
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), &[1, 2, 3, 4]);
+}
+
+#[test]
+fn context_wait_runtime() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-context-wait-runtime");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    appsrc.set_property("context-wait", 50u32);
+
+    // The task might have been waiting for an item before the change
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let start = std::time::Instant::now();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(25));
+
+    appsrc.set_property("context-wait", 0u32);
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+}
+
+#[test]
+fn interval_stats() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-interval-stats");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        let _ = h.pull().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+
+    // Rejected pushes don't count
+    appsrc.set_property("max-buffer-size", 1u64);
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::with_size(2).unwrap()]));
+
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    assert_eq!(stats.get::<u64>("interval-count").unwrap(), 2);
+
+    let min = stats.get::<u64>("interval-min").unwrap();
+    let max = stats.get::<u64>("interval-max").unwrap();
+    let mean = stats.get::<u64>("interval-mean").unwrap();
+    assert!(min >= gst::ClockTime::from_mseconds(10).nseconds());
+    assert!(min <= mean && mean <= max);
+}
+
+#[test]
+fn end_of_stream_immediate() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-end-of-stream-immediate");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream-immediate", &[]));
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+}
+
+#[test]
+fn max_buffer_size() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("max-buffer-size", 4u64);
+        appsrc.set_property("context", "appsrc-max-buffer-size");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::with_size(5).unwrap()]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::with_size(4).unwrap()]));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.size(), 4);
+}
+
+#[test]
+fn state_changed() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let transitions = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-state-changed");
+        appsrc.connect("state-changed", false, {
+            let transitions = transitions.clone();
+            move |args| {
+                let old_state = args[1].get::<String>().unwrap();
+                let new_state = args[2].get::<String>().unwrap();
+                transitions.lock().unwrap().push((old_state, new_state));
+                None
+            }
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_state(gst::State::Ready).unwrap();
+
+    let transitions = transitions.lock().unwrap();
+    let expected = [
+        ("Unprepared", "Prepared"),
+        ("Prepared", "Started"),
+        ("Started", "Paused"),
+        ("Paused", "Stopped"),
+    ];
+    assert_eq!(transitions.len(), expected.len());
+    for ((old_state, new_state), (expected_old, expected_new)) in
+        transitions.iter().zip(expected.iter())
+    {
+        assert_eq!(old_state, expected_old);
+        assert_eq!(new_state, expected_new);
+    }
+}
+
+#[test]
+fn push_buffer_full_meta() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-push-buffer-full-meta");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>(
+        "push-buffer-full-meta",
+        &[
+            &gst::Buffer::new(),
+            &gst::ClockTime::from_seconds(2).nseconds(),
+            &u64::MAX,
+            &gst::ClockTime::SECOND.nseconds(),
+            &10u64,
+            &u64::MAX,
+        ],
+    ));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(2)));
+    // Not timestamped by do-timestamp
+    assert_eq!(buffer.dts(), None);
+    assert_eq!(buffer.duration(), Some(gst::ClockTime::SECOND));
+    assert_eq!(buffer.offset(), 10);
+    assert_eq!(buffer.offset_end(), gst::BUFFER_OFFSET_NONE);
+}
+
+#[test]
+fn renegotiate() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-renegotiate");
+        appsrc.connect("renegotiate", false, {
+            let new_caps = new_caps.clone();
+            move |_| Some(Some(new_caps.clone()).to_value())
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    assert!(h.push_upstream_event(gst::event::Reconfigure::new()));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let mut last_caps = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            last_caps = Some(ev.caps_owned());
+        }
+    }
+    assert_eq!(last_caps, Some(new_caps.clone()));
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("current-caps"),
+        Some(new_caps)
+    );
+}
+
+#[test]
+fn spill_to_disk() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("max-buffers", 1u32);
+        appsrc.set_property("spill-to-disk", true);
+        appsrc.set_property("context", "appsrc-spill-to-disk");
+    }
+
+    h.play();
+
+    // Pause the task so that the items pile up
+    let appsrc = h.element().unwrap();
+    appsrc.set_state(gst::State::Paused).unwrap();
+
+    let make_buffer = |idx: u8| {
+        let mut buffer = gst::Buffer::from_slice([idx; 4]);
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_seconds(idx as u64));
+        buffer
+    };
+
+    for idx in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(idx)]));
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-with-caps", &[&new_caps, &make_buffer(3)]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(4)]));
+
+    appsrc.set_state(gst::State::Playing).unwrap();
+
+    for idx in 0..5u8 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_seconds(idx as u64)));
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), &[idx; 4]);
+    }
+
+    let mut last_caps = None;
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            last_caps = Some(ev.caps_owned());
+        }
+    }
+    assert_eq!(last_caps, Some(new_caps));
+}
+
+#[test]
+fn spill_to_disk_metas() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("max-buffers", 1u32);
+    appsrc.set_property("spill-to-disk", true);
+    appsrc.set_property("context", "appsrc-spill-to-disk-metas");
+
+    h.play();
+
+    // Pause the task so that the items pile up
+    appsrc.set_state(gst::State::Paused).unwrap();
+
+    let reference_caps = gst::Caps::builder("timestamp/x-test").build();
+    let make_buffer = |idx: u8, with_meta: bool| {
+        let mut buffer = gst::Buffer::from_slice([idx; 4]);
+        if with_meta {
+            gst::ReferenceTimestampMeta::add(
+                buffer.get_mut().unwrap(),
+                &reference_caps,
+                gst::ClockTime::from_seconds(idx as u64),
+                gst::ClockTime::NONE,
+            );
+        }
+        buffer
+    };
+
+    // Queued, written to the spill file, then kept in memory along with its meta
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(0, false)]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(1, false)]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(2, true)]));
+    // Only max-buffers items are kept in memory
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(3, true)]));
+    assert!(!appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+
+    appsrc.set_state(gst::State::Playing).unwrap();
+
+    for idx in 0..3u8 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), &[idx; 4]);
+        assert_eq!(
+            buffer.meta::<gst::ReferenceTimestampMeta>().is_some(),
+            idx == 2
+        );
+    }
+    assert!(h.try_pull().is_none());
+}
+
+#[test]
+fn oldest_buffer_age() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-oldest-buffer-age");
+
+    h.play();
+
+    // Pause the task so that the buffer stays queued
+    let appsrc = h.element().unwrap();
+    appsrc.set_state(gst::State::Paused).unwrap();
+    assert_eq!(appsrc.property::<u64>("oldest-buffer-age"), 0);
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    std::thread::sleep(std::time::Duration::from_millis(20));
+
+    let min_age = gst::ClockTime::from_mseconds(20).nseconds();
+    assert!(appsrc.property::<u64>("oldest-buffer-age") >= min_age);
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    assert!(stats.get::<u64>("oldest-buffer-age").unwrap() >= min_age);
+
+    appsrc.set_state(gst::State::Playing).unwrap();
+    let _ = h.pull().unwrap();
+    assert_eq!(appsrc.property::<u64>("oldest-buffer-age"), 0);
+}
+
+#[test]
+fn retry_not_linked() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", "appsrc-retry-not-linked")
+        .property("max-retries", 100u32)
+        .property(
+            "retry-interval",
+            gst::ClockTime::from_mseconds(10).nseconds(),
+        )
+        .build()
+        .unwrap();
+    appsrc.set_property_from_str("retry-errors", "not-linked");
+
+    let sink = gst_app::AppSink::builder()
+        .sync(false)
+        .async_(false)
+        .build();
+
+    pipeline.add_many([&appsrc, sink.upcast_ref()]).unwrap();
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    // Link while the task is retrying
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    appsrc.link(&sink).unwrap();
+
+    let sample = sink.try_pull_sample(gst::ClockTime::from_seconds(5));
+    assert!(sample.is_some());
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn push_buffer_wait_level() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-push-buffer-wait-level");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-wait-level", &[&gst::Buffer::new(), &0u32]));
+    let _ = h.pull().unwrap();
+
+    // Stopping unblocks the waiter
+    appsrc.set_state(gst::State::Paused).unwrap();
+    let waiter = std::thread::spawn({
+        let appsrc = appsrc.clone();
+        move || appsrc.emit_by_name::<bool>("push-buffer-wait-level", &[&gst::Buffer::new(), &0u32])
+    });
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    appsrc.set_state(gst::State::Ready).unwrap();
+    assert!(!waiter.join().unwrap());
+}
+
+#[test]
+fn auto_discont() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-auto-discont");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    for _ in 0..2 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    assert!(h
+        .pull()
+        .unwrap()
+        .flags()
+        .contains(gst::BufferFlags::DISCONT));
+    assert!(!h
+        .pull()
+        .unwrap()
+        .flags()
+        .contains(gst::BufferFlags::DISCONT));
+
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert!(h
+        .pull()
+        .unwrap()
+        .flags()
+        .contains(gst::BufferFlags::DISCONT));
+}
+
+#[test]
+fn runtime_context() {
+    use gstthreadshare::appsrc::AppSrc;
+    use gstthreadshare::runtime::Context;
+
+    init();
+
+    let context = Context::acquire("appsrc-runtime-context", std::time::Duration::ZERO).unwrap();
+
+    let elements = (0..2)
+        .map(|_| {
+            let appsrc = gst::ElementFactory::make("ts-appsrc")
+                // Would lead to a different Context if it was used
+                .property("context", "appsrc-runtime-context-unused")
+                .build()
+                .unwrap()
+                .downcast::<AppSrc>()
+                .unwrap();
+            appsrc.set_runtime_context(Some(context.clone()));
+            appsrc
+        })
+        .collect::<Vec<_>>();
+
+    for appsrc in elements.iter() {
+        assert!(appsrc.runtime_context().is_none());
+        appsrc.set_state(gst::State::Ready).unwrap();
+        assert_eq!(appsrc.runtime_context().unwrap().name(), context.name());
+    }
+
+    for appsrc in elements.iter() {
+        appsrc.set_state(gst::State::Null).unwrap();
+        assert!(appsrc.runtime_context().is_none());
+    }
+}
+
+#[test]
+fn do_timestamp_testclock() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property(
+            "capture-latency",
+            gst::ClockTime::from_mseconds(10).nseconds(),
+        );
+        appsrc.set_property("context", "appsrc-do-timestamp-testclock");
+    }
+
+    // The harness installs the controllable clock on the element
+    h.use_testclock();
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let base_time = appsrc.base_time().unwrap();
+
+    for ms in [1000, 1040, 1100] {
+        h.set_time(base_time + gst::ClockTime::from_mseconds(ms))
+            .unwrap();
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.dts(), Some(gst::ClockTime::from_mseconds(ms - 10)),);
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(ms - 10)));
+    }
+}
+
+#[test]
+fn push_bytes() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-push-bytes");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let bytes = gst::glib::Bytes::from_static(&[1, 2, 3, 4]);
+    assert!(appsrc.emit_by_name::<bool>("push-bytes", &[&bytes]));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), &[1, 2, 3, 4]);
+    assert!(buffer.dts().is_some());
+}
+
+#[test]
+fn buffer_event_ordering() {
+    use std::sync::{Arc, Mutex};
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-buffer-event-ordering");
+
+    // Record the serialized items in the order they leave the element
+    let order = Arc::new(Mutex::new(Vec::new()));
+    h.element()
+        .unwrap()
+        .static_pad("src")
+        .unwrap()
+        .add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::EVENT_DOWNSTREAM,
+            {
+                let order = order.clone();
+                move |_, info| {
+                    match info.data {
+                        Some(gst::PadProbeData::Buffer(ref buffer)) => order
+                            .lock()
+                            .unwrap()
+                            .push(format!("buffer-{}", buffer.offset())),
+                        Some(gst::PadProbeData::Event(ref event)) => {
+                            if let Some(s) = event.structure().filter(|s| s.name() == "marker") {
+                                let idx = s.get::<u32>("idx").unwrap();
+                                order.lock().unwrap().push(format!("event-{idx}"));
+                            }
+                        }
+                        _ => (),
+                    }
+                    gst::PadProbeReturn::Ok
+                }
+            },
+        )
+        .unwrap();
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let mut expected = Vec::new();
+    for idx in 0..10u32 {
+        let mut buffer = gst::Buffer::new();
+        buffer.get_mut().unwrap().set_offset(idx as u64);
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+        expected.push(format!("buffer-{idx}"));
+
+        let event = gst::event::CustomDownstream::new(
+            gst::Structure::builder("marker").field("idx", idx).build(),
+        );
+        assert!(appsrc.emit_by_name::<bool>("push-event", &[&event]));
+        expected.push(format!("event-{idx}"));
+    }
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+
+    for _ in 0..10 {
+        let _ = h.pull().unwrap();
+    }
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    assert_eq!(*order.lock().unwrap(), expected);
+}
+
+#[test]
+fn num_buffers() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("num-buffers", 2i32);
+        appsrc.set_property("context", "appsrc-num-buffers");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+
+    let _ = h.pull().unwrap();
+    let _ = h.pull().unwrap();
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    // The third buffer is not forwarded
+    assert_eq!(h.buffers_received(), 2);
+    assert!(h.try_pull().is_none());
+}
+
+#[test]
+fn num_buffers_zero() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("num-buffers", 0i32);
+        appsrc.set_property("context", "appsrc-num-buffers-zero");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    assert_eq!(h.buffers_received(), 0);
+    assert!(h.try_pull().is_none());
+}
+
+#[test]
+fn derived_latency() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("framerate", gst::Fraction::new(25, 1))
+        .build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("max-buffers", 5u32);
+        appsrc.set_property("context", "appsrc-derived-latency");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let pad = appsrc.static_pad("src").unwrap();
+
+    let mut q = gst::query::Latency::new();
+    assert!(pad.query(&mut q));
+    assert_eq!(
+        q.result(),
+        (
+            true,
+            gst::ClockTime::from_mseconds(200),
+            gst::ClockTime::NONE
+        )
+    );
+    assert_eq!(
+        appsrc.property::<u64>("latency"),
+        gst::ClockTime::from_mseconds(200).nseconds()
+    );
+
+    // An explicit capture-latency takes precedence
+    appsrc.set_property(
+        "capture-latency",
+        gst::ClockTime::from_mseconds(30).nseconds(),
+    );
+    let mut q = gst::query::Latency::new();
+    assert!(pad.query(&mut q));
+    assert_eq!(
+        q.result(),
+        (
+            true,
+            gst::ClockTime::from_mseconds(30),
+            gst::ClockTime::NONE
+        )
+    );
+}
+
+#[test]
+fn derived_latency_message() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = |width: i32, framerate: i32| {
+        gst::Caps::builder("video/x-raw")
+            .field("width", width)
+            .field("framerate", gst::Fraction::new(framerate, 1))
+            .build()
+    };
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", caps(320, 25));
+        appsrc.set_property("context", "appsrc-derived-latency-message");
+    }
+
+    let bus = gst::Bus::new();
+    h.element().unwrap().set_bus(Some(&bus));
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let mut push_caps = |caps: gst::Caps| {
+        appsrc.set_property("caps", caps);
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        let _ = h.pull().unwrap();
+    };
+
+    // Same framerate, same latency
+    push_caps(caps(320, 25));
+    push_caps(caps(640, 25));
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_none());
+
+    push_caps(caps(640, 50));
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_some());
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_none());
+}
+
+#[test]
+fn warn_on_full() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("max-buffers", 1u32);
+        appsrc.set_property("warn-on-full", true);
+        appsrc.set_property("context", "appsrc-warn-on-full");
+    }
+
+    let bus = gst::Bus::new();
+    h.element().unwrap().set_bus(Some(&bus));
+
+    // Not started: the queue can't be drained
+    h.element().unwrap().set_state(gst::State::Ready).unwrap();
+    let appsrc = h.element().unwrap();
+    let _ = appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]);
+
+    // Only one warning despite the multiple drops
+    for _ in 0..5 {
+        let _ = appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]);
+    }
+
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(1),
+            &[gst::MessageType::Warning],
+        )
+        .unwrap();
+    match msg.view() {
+        gst::MessageView::Warning(warning) => {
+            assert!(warning.error().matches(gst::ResourceError::NoSpaceLeft))
+        }
+        _ => unreachable!(),
+    }
+    assert!(bus.pop_filtered(&[gst::MessageType::Warning]).is_none());
+
+    h.element().unwrap().set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn configure() {
+    init();
+
+    let h = gst_check::Harness::new("ts-appsrc");
+    let appsrc = h.element().unwrap();
+
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 20u32)
+        .field("do-timestamp", true)
+        .field("format", gst::Format::Time)
+        .build();
+    assert!(appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+    assert!(appsrc.property::<bool>("do-timestamp"));
+
+    // Invalid combination: nothing is applied
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 30u32)
+        .field("format", gst::Format::Bytes)
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+    assert_eq!(appsrc.property::<gst::Format>("format"), gst::Format::Time);
+
+    // Rejected value: nothing is applied
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 30u32)
+        .field(
+            "stream-flags",
+            gst::StreamFlags::SELECT | gst::StreamFlags::UNSELECT,
+        )
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+
+    let config = gst::Structure::builder("config")
+        .field("low-watermark", 0.9f64)
+        .field("high-watermark", 0.95f64)
+        .build();
+    assert!(appsrc.emit_by_name::<bool>("configure", &[&config]));
+
+    // Watermarks are checked once all are set
+    let config = gst::Structure::builder("config")
+        .field("high-watermark", 0.5f64)
+        .field("low-watermark", 0.3f64)
+        .build();
+    assert!(appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<f64>("high-watermark"), 0.5);
+    assert_eq!(appsrc.property::<f64>("low-watermark"), 0.3);
+
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 30u32)
+        .field("low-watermark", 0.6f64)
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+    assert_eq!(appsrc.property::<f64>("low-watermark"), 0.3);
+
+    // Unknown setting
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 30u32)
+        .field("unknown", true)
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+
+    // Invalid type
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", "30")
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+
+    // Out of range
+    let config = gst::Structure::builder("config")
+        .field("max-buffers", 0u32)
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.property::<u32>("max-buffers"), 20);
+
+    // Inherited property, not a setting
+    let name = appsrc.name();
+    let config = gst::Structure::builder("config")
+        .field("name", "renamed")
+        .build();
+    assert!(!appsrc.emit_by_name::<bool>("configure", &[&config]));
+    assert_eq!(appsrc.name(), name);
+}
+
+#[test]
+#[cfg(feature = "v1_20")]
+fn caps_override_meta() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-caps-override-meta");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let override_caps = gst::Caps::builder("foo/baz").build();
+    let mut buffer = gst::Buffer::new();
+    {
+        let mut meta = gst::meta::CustomMeta::add(
+            buffer.get_mut().unwrap(),
+            gstthreadshare::appsrc::CAPS_META_NAME,
+        )
+        .unwrap();
+        meta.mut_structure().set("caps", &override_caps);
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    // The meta is only meant for ts-appsrc
+    let buffer = h.pull().unwrap();
+    assert!(
+        gst::meta::CustomMeta::from_buffer(&buffer, gstthreadshare::appsrc::CAPS_META_NAME)
+            .is_err()
+    );
+    let _ = h.pull().unwrap();
+
+    let mut caps_events = vec![];
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            caps_events.push(ev.caps_owned());
+        }
+    }
+    assert_eq!(caps_events, vec![caps.clone(), override_caps, caps.clone()]);
+
+    // The stream caps are unchanged
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("current-caps"),
+        Some(caps)
+    );
+}
+
+#[test]
+fn dump_state() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("max-buffers", 5u32);
+        appsrc.set_property("context", "appsrc-dump-state");
+    }
+
+    // Safe to call before the element is prepared
+    let appsrc = h.element().unwrap();
+    let state = appsrc.emit_by_name::<gst::Structure>("dump-state", &[]);
+    assert_eq!(state.get::<&str>("task-state").unwrap(), "Unprepared");
+    assert!(!state.has_field("current-caps"));
+
+    h.play();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let state = appsrc.emit_by_name::<gst::Structure>("dump-state", &[]);
+    assert_eq!(state.get::<&str>("element-state").unwrap(), "Playing");
+    assert_eq!(state.get::<&str>("task-state").unwrap(), "Started");
+    assert_eq!(state.get::<u32>("queued-buffers").unwrap(), 0);
+    assert_eq!(state.get::<u64>("buffer-count").unwrap(), 1);
+    assert_eq!(state.get::<gst::Caps>("current-caps").unwrap(), caps);
+    assert!(state.get::<gst::Segment>("segment").is_ok());
+
+    let settings = state.get::<gst::Structure>("settings").unwrap();
+    assert_eq!(settings.get::<&str>("max-buffers").unwrap(), "5");
+    assert_eq!(
+        settings.get::<&str>("context").unwrap(),
+        "appsrc-dump-state"
+    );
+    assert!(state.get::<gst::Structure>("stats").is_ok());
+}
+
+#[test]
+fn stream_id_seed() {
+    init();
+
+    let first_stream_id = |context: &str| {
+        let mut h = gst_check::Harness::new("ts-appsrc");
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", context);
+        appsrc.set_property("stream-id-seed", 42u64);
+        h.play();
+
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        let _ = h.pull().unwrap();
+
+        let event = h.pull_event().unwrap();
+        match event.view() {
+            gst::EventView::StreamStart(ev) => ev.stream_id().to_owned(),
+            _ => panic!("Unexpected event {event:?}"),
+        }
+    };
+
+    let stream_id1 = first_stream_id("appsrc-stream-id-seed-1");
+    let stream_id2 = first_stream_id("appsrc-stream-id-seed-2");
+
+    assert_eq!(stream_id1, stream_id2);
+}
+
+#[test]
+fn configured_latency() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-configured-latency");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert_eq!(appsrc.property::<u64>("configured-latency"), 0);
+
+    let latency = gst::ClockTime::from_mseconds(40);
+    assert!(h.push_upstream_event(gst::event::Latency::new(latency)));
+    assert_eq!(
+        appsrc.property::<u64>("configured-latency"),
+        latency.nseconds()
+    );
+}
+
+#[test]
+fn fill_on_underrun() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let mut filler = gst::Buffer::from_slice([0u8; 4]);
+    filler
+        .get_mut()
+        .unwrap()
+        .set_duration(gst::ClockTime::from_mseconds(20));
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("format", gst::Format::Time);
+        appsrc.set_property("fill-on-underrun", true);
+        appsrc.set_property("filler-buffer", &filler);
+        appsrc.set_property("context", "appsrc-fill-on-underrun");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let mut buffer = gst::Buffer::from_slice([1u8; 4]);
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::ZERO);
+        buffer.set_duration(gst::ClockTime::from_mseconds(20));
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    // Fillers might have been pushed before the buffer was queued
+    loop {
+        let buffer = h.pull().unwrap();
+        if !buffer.flags().contains(gst::BufferFlags::GAP) {
+            break;
+        }
+    }
+
+    // Nothing queued: fillers follow the last buffer
+    let buffer = h.pull().unwrap();
+    assert!(buffer.flags().contains(gst::BufferFlags::GAP));
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(20)));
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), &[0u8; 4]);
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(40)));
+}
+
+#[test]
+fn dwell_stats() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-stats", true);
+        appsrc.set_property("context", "appsrc-dwell-stats");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // Buffers are kept in the queue while paused
+    appsrc
+        .change_state(gst::StateChange::PlayingToPaused)
+        .unwrap();
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    appsrc
+        .change_state(gst::StateChange::PausedToPlaying)
+        .unwrap();
+
+    for _ in 0..3 {
+        let _ = h.pull().unwrap();
+    }
+
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    assert_eq!(stats.get::<u64>("dwell-count").unwrap(), 3);
+    let min = stats.get::<u64>("dwell-min").unwrap();
+    assert!(min >= gst::ClockTime::from_mseconds(20).nseconds());
+    assert!(stats.get::<u64>("dwell-max").unwrap() >= min);
+    assert!(stats.get::<u64>("dwell-median").unwrap() >= min);
+}
+
+#[test]
+fn reconfigure_playing() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("context", "appsrc-reconfigure-playing");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().is_none());
+
+    // caps: sent before the next buffer
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    appsrc.set_property("caps", &new_caps);
+    // do-timestamp: applies to the next pushed buffer
+    appsrc.set_property("do-timestamp", true);
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().is_some());
+
+    let mut caps_events = vec![];
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            caps_events.push(ev.caps_owned());
+        }
+    }
+    assert_eq!(caps_events, vec![caps, new_caps.clone()]);
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("current-caps"),
+        Some(new_caps)
+    );
+
+    // capture-latency: reported by the next latency query
+    let latency = gst::ClockTime::from_mseconds(10);
+    appsrc.set_property("capture-latency", latency.nseconds());
+    let mut q = gst::query::Latency::new();
+    assert!(appsrc.static_pad("src").unwrap().query(&mut q));
+    assert_eq!(q.result().1, latency);
+}
+
+#[test]
+fn explicit_flush() {
+    use gstthreadshare::appsrc::AppSrc;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-explicit-flush");
+
+    let appsrc = h.element().unwrap().downcast::<AppSrc>().unwrap();
+
+    // Can't flush before the element is started
+    assert!(appsrc.flush().is_err());
+
+    h.play();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    // Keep the next buffers in the queue
+    appsrc
+        .change_state(gst::StateChange::PlayingToPaused)
+        .unwrap();
+    for _ in 0..2 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+
+    appsrc.flush().unwrap();
+
+    appsrc
+        .change_state(gst::StateChange::PausedToPlaying)
+        .unwrap();
+
+    // Queued buffers were dropped
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+    assert!(h.try_pull().is_none());
+
+    // No flush events downstream, but a new segment
+    let mut segments = 0;
+    while let Some(event) = h.try_pull_event() {
+        assert_ne!(event.type_(), gst::EventType::FlushStart);
+        assert_ne!(event.type_(), gst::EventType::FlushStop);
+        if event.type_() == gst::EventType::Segment {
+            segments += 1;
+        }
+    }
+    assert_eq!(segments, 2);
+}
+
+#[test]
+fn push_buffer_protected() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        // Timestamping makes the buffer writable
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-push-buffer-protected");
+    }
+
+    h.play();
+
+    let info = gst::Structure::builder("application/x-cenc")
+        .field("kid", gst::Buffer::from_slice([0u8; 16]))
+        .field("iv_size", 8u32)
+        .build();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-protected", &[&gst::Buffer::new(), &info]));
+
+    let buffer = h.pull().unwrap();
+    assert!(buffer.dts().is_some());
+
+    let meta = buffer.meta::<gst::ProtectionMeta>().unwrap();
+    assert_eq!(meta.info().as_ref(), info.as_ref());
+}
+
+#[test]
+fn data_available() {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let count = Arc::new(AtomicU32::new(0));
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-data-available");
+
+        let count = count.clone();
+        appsrc.connect("data-available", false, move |_| {
+            count.fetch_add(1, Ordering::SeqCst);
+            None
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // Only the first of the buffers queued while paused triggers the signal
+    appsrc
+        .change_state(gst::StateChange::PlayingToPaused)
+        .unwrap();
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    assert_eq!(count.load(Ordering::SeqCst), 1);
+
+    appsrc
+        .change_state(gst::StateChange::PausedToPlaying)
+        .unwrap();
+    for _ in 0..3 {
+        let _ = h.pull().unwrap();
+    }
+
+    // The queue was drained
+    appsrc
+        .change_state(gst::StateChange::PlayingToPaused)
+        .unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert_eq!(count.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn reorder_window() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("format", gst::Format::Time);
+        appsrc.set_property(
+            "reorder-window",
+            gst::ClockTime::from_mseconds(50).nseconds(),
+        );
+        appsrc.set_property("context", "appsrc-reorder-window");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let push = |pts: u64| {
+        let mut buffer = gst::Buffer::new();
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(pts));
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    };
+
+    for pts in [20, 0, 10] {
+        push(pts);
+    }
+    for pts in [0, 10, 20] {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+    }
+
+    // Arrives after a buffer with a greater PTS was forwarded: dropped
+    push(5);
+    push(30);
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(30)));
+
+    // Held buffers are forwarded before the EOS
+    push(50);
+    push(40);
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    for pts in [40, 50] {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+    }
+}
+
+#[test]
+fn allocation() {
+    use std::sync::{Arc, Mutex};
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let params = Arc::new(Mutex::new(Vec::new()));
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", gst::Caps::builder("foo/bar").build());
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-allocation");
+
+        let params = params.clone();
+        appsrc.connect("allocation", false, move |args| {
+            params
+                .lock()
+                .unwrap()
+                .push(args[3].get::<gst::Structure>().unwrap());
+            None
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    // Answered once, after the caps were sent
+    let params = params.lock().unwrap();
+    assert_eq!(params.len(), 1);
+    assert!(params[0].has_field("min-buffers"));
+    assert!(params[0].has_field("align"));
+}
+
+#[test]
+fn end_of_stream_with_info() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-end-of-stream-with-info");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let info = gst::Structure::builder("final-stats")
+        .field("frames", 2u64)
+        .build();
+
+    for _ in 0..2 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream-with-info", &[&info]));
+
+    // Queued buffers are pushed first
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+
+    let mut events = vec![];
+    loop {
+        let event = h.pull_event().unwrap();
+        let is_eos = event.type_() == gst::EventType::Eos;
+        events.push(event);
+        if is_eos {
+            break;
+        }
+    }
+
+    let custom = &events[events.len() - 2];
+    assert_eq!(custom.type_(), gst::EventType::CustomDownstream);
+    assert_eq!(custom.structure().unwrap(), info.as_ref());
+}
+
+#[test]
+fn is_live() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("is-live", false);
+    appsrc.set_property("context", "appsrc-is-live");
+
+    let bus = gst::Bus::new();
+    appsrc.set_bus(Some(&bus));
+
+    // Non-live: streams in PAUSED to preroll
+    assert_eq!(
+        appsrc.set_state(gst::State::Paused),
+        Ok(gst::StateChangeSuccess::Success)
+    );
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let pad = appsrc.static_pad("src").unwrap();
+    let mut q = gst::query::Latency::new();
+    assert!(pad.query(&mut q));
+    assert!(!q.result().0);
+
+    // Switching to live asks the pipeline to recompute the latency
+    appsrc.set_property("is-live", true);
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_some());
+    let mut q = gst::query::Latency::new();
+    assert!(pad.query(&mut q));
+    assert!(q.result().0);
+
+    assert_eq!(
+        appsrc.set_state(gst::State::Playing),
+        Ok(gst::StateChangeSuccess::Success)
+    );
+    assert_eq!(
+        appsrc.set_state(gst::State::Paused),
+        Ok(gst::StateChangeSuccess::NoPreroll)
+    );
+
+    appsrc.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn clip_to_segment() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-clip-to-segment");
+        appsrc.set_property("clip-to-segment", true);
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(gst::ClockTime::from_mseconds(100));
+    segment.set_stop(gst::ClockTime::from_mseconds(200));
+    assert!(appsrc.emit_by_name::<bool>("push-event", &[&gst::event::Segment::new(&segment)]));
+
+    for pts in [0, 80, 120, 180, 250] {
+        let mut buffer = gst::Buffer::new();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(pts));
+            buffer.set_duration(gst::ClockTime::from_mseconds(40));
+        }
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    }
+
+    // 0 and 250 are outside the segment, 80 and 180 overlap its boundaries
+    let expected = [(100, 20), (120, 40), (180, 20)];
+    for (pts, duration) in expected {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+        assert_eq!(
+            buffer.duration(),
+            Some(gst::ClockTime::from_mseconds(duration))
+        );
+    }
+
+    assert!(h.try_pull().is_none());
+}
+
+#[test]
+fn buffer_stream() {
+    use gstthreadshare::appsrc::AppSrc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap().downcast::<AppSrc>().unwrap();
+    appsrc.set_property("context", "appsrc-buffer-stream");
+
+    let subscriptions = Arc::new(AtomicUsize::new(0));
+    appsrc.set_buffer_stream({
+        let subscriptions = subscriptions.clone();
+        move || {
+            subscriptions.fetch_add(1, Ordering::SeqCst);
+            futures::stream::iter((0..3).map(|pts| {
+                let mut buffer = gst::Buffer::new();
+                buffer
+                    .get_mut()
+                    .unwrap()
+                    .set_pts(gst::ClockTime::from_mseconds(pts));
+                buffer
+            }))
+        }
+    });
+
+    h.play();
+
+    for pts in 0..3 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+    }
+
+    // The end of the stream is handled as an EOS
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    assert_eq!(subscriptions.load(Ordering::SeqCst), 1);
+
+    // Restarting subscribes again
+    appsrc.set_state(gst::State::Ready).unwrap();
+    appsrc.set_state(gst::State::Playing).unwrap();
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(subscriptions.load(Ordering::SeqCst), 2);
+
+    appsrc.unset_buffer_stream();
+    appsrc.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn do_timestamp_mode() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property_from_str("do-timestamp-mode", "if-missing");
+        appsrc.set_property("context", "appsrc-do-timestamp-mode");
+    }
+
+    h.use_testclock();
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let base_time = appsrc.base_time().unwrap();
+    h.set_time(base_time + gst::ClockTime::from_mseconds(1000))
+        .unwrap();
+
+    // Application timestamps are preserved
+    let mut buffer = gst::Buffer::new();
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_mseconds(42));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(42)));
+    assert_eq!(buffer.dts(), None);
+
+    // Buffers without timestamps are timestamped
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.dts(), Some(gst::ClockTime::from_mseconds(1000)));
+
+    appsrc.set_property_from_str("do-timestamp-mode", "never");
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.dts(), None);
+    assert_eq!(buffer.pts(), None);
+}
+
+#[test]
+fn split_size() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-split-size");
+        appsrc.set_property("split-size", 4u32);
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let mut buffer = gst::Buffer::from_slice((0u8..10).collect::<Vec<_>>());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::from_mseconds(100));
+        buffer.set_duration(gst::ClockTime::from_mseconds(100));
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    let expected: [(&[u8], u64, u64); 3] = [
+        (&[0, 1, 2, 3], 100, 40),
+        (&[4, 5, 6, 7], 140, 40),
+        (&[8, 9], 180, 20),
+    ];
+    for (data, pts, duration) in expected {
+        let chunk = h.pull().unwrap();
+        assert_eq!(chunk.map_readable().unwrap().as_slice(), data);
+        assert_eq!(chunk.pts(), Some(gst::ClockTime::from_mseconds(pts)));
+        assert_eq!(
+            chunk.duration(),
+            Some(gst::ClockTime::from_mseconds(duration))
+        );
+        // Only the first chunk follows a discontinuity
+        assert_eq!(
+            chunk.flags().contains(gst::BufferFlags::DISCONT),
+            pts == 100
+        );
+    }
+
+    // Smaller buffers are left untouched
+    let buffer = gst::Buffer::from_slice([0u8; 4]);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    assert_eq!(h.pull().unwrap().size(), 4);
+}
+
+#[test]
+fn eos_debounce() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-eos-debounce");
+        appsrc.set_property("eos-debounce", 200u32);
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // A stream reset within the window cancels the EOS
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    assert!(appsrc.emit_by_name::<bool>("reset-stream", &[]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+    while let Some(event) = h.try_pull_event() {
+        assert_ne!(event.type_(), gst::EventType::Eos);
+    }
+
+    // Otherwise the EOS is pushed once the window elapsed
+    let start = std::time::Instant::now();
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    assert!(start.elapsed() >= std::time::Duration::from_millis(200));
+}
+
+#[test]
+fn error_detailed() {
+    use gstthreadshare::appsrc::ErrorCode;
+    use std::sync::mpsc;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+    h.set_sink_caps_str("audio/x-raw");
+
+    // Can't be fixated to the sink caps
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("width", gst::IntRange::new(1, 10))
+        .build();
+
+    let (details_tx, details_rx) = mpsc::sync_channel(1);
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-error-detailed");
+
+        appsrc.connect("error-detailed", false, move |args| {
+            let _ = details_tx.try_send(args[1].get::<gst::Structure>().unwrap());
+            None
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let mut buffer = gst::Buffer::with_size(4).unwrap();
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_mseconds(10));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    let details = details_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(
+        details.get::<ErrorCode>("code").unwrap(),
+        ErrorCode::NotNegotiated
+    );
+    assert_eq!(details.get::<gst::Caps>("caps").unwrap(), caps);
+    assert_eq!(details.get::<u64>("buffer-size").unwrap(), 4);
+    assert_eq!(
+        details.get::<gst::ClockTime>("buffer-pts").unwrap(),
+        gst::ClockTime::from_mseconds(10)
+    );
+}
+
+#[test]
+fn lock_caps() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("caps", &caps);
+        appsrc.set_property("lock-caps", true);
+        appsrc.set_property("context", "appsrc-lock-caps");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    // Caps changes are rejected once the caps were sent
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    appsrc.set_property("caps", &new_caps);
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("caps"),
+        Some(caps.clone())
+    );
+    assert!(
+        !appsrc.emit_by_name::<bool>("push-buffer-with-caps", &[&new_caps, &gst::Buffer::new()])
+    );
+
+    // Unchanged caps are accepted
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-with-caps", &[&caps, &gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            assert_eq!(ev.caps(), caps.as_ref());
+        }
+    }
+}
+
+#[test]
+fn lock_caps_superset() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").field("x", 1i32).build();
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("caps", &caps);
+    appsrc.set_property("lock-caps", true);
+    appsrc.set_property("context", "appsrc-lock-caps-superset");
+
+    h.play();
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    // Less specific caps would change the caps sent downstream
+    let superset_caps = gst::Caps::builder("foo/bar").build();
+    appsrc.set_property("caps", &superset_caps);
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("caps"),
+        Some(caps.clone())
+    );
+    assert!(!appsrc.emit_by_name::<bool>(
+        "push-buffer-with-caps",
+        &[&superset_caps, &gst::Buffer::new()]
+    ));
+}
+
+#[test]
+fn push_buffer_deadline() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-push-buffer-deadline");
+
+    h.use_testclock();
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let base_time = appsrc.base_time().unwrap();
+    h.set_time(base_time + gst::ClockTime::from_mseconds(1000))
+        .unwrap();
+
+    let push_with_deadline = |pts: u64, deadline: u64| {
+        let mut buffer = gst::Buffer::new();
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(pts));
+        appsrc.emit_by_name::<bool>(
+            "push-buffer-deadline",
+            &[&buffer, &gst::ClockTime::from_mseconds(deadline).nseconds()],
+        )
+    };
+
+    // The first deadline passed already
+    assert!(push_with_deadline(0, 500));
+    assert!(push_with_deadline(10, 2000));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(10)));
+
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    assert_eq!(stats.get::<u64>("deadline-drops").unwrap(), 1);
+}
+
+#[test]
+fn latency_messages() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("framerate", gst::Fraction::new(25, 1))
+        .build();
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("caps", &caps);
+    appsrc.set_property("context", "appsrc-latency-messages");
+
+    let bus = gst::Bus::new();
+    appsrc.set_bus(Some(&bus));
+
+    h.play();
+    while bus.pop().is_some() {}
+
+    // The derived latency depends on max-buffers
+    appsrc.set_property("max-buffers", 5u32);
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_some());
+
+    // Holding buffers for reordering adds latency
+    appsrc.set_property(
+        "reorder-window",
+        gst::ClockTime::from_mseconds(20).nseconds(),
+    );
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_some());
+    assert_eq!(
+        appsrc.property::<u64>("latency"),
+        gst::ClockTime::from_mseconds(220).nseconds()
+    );
+
+    // Unchanged latency
+    appsrc.set_property("max-buffers", 5u32);
+    assert!(bus.pop_filtered(&[gst::MessageType::Latency]).is_none());
+
+    appsrc.set_bus(None);
+}
+
+#[test]
+fn preroll() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", "appsrc-preroll")
+        .property("is-live", false)
+        .build()
+        .unwrap();
+    let fakesink = gst::ElementFactory::make("fakesink").build().unwrap();
+    pipeline.add_many([&appsrc, &fakesink]).unwrap();
+    appsrc.link(&fakesink).unwrap();
+
+    // The sink waits for a preroll buffer
+    assert_eq!(
+        pipeline.set_state(gst::State::Paused),
+        Ok(gst::StateChangeSuccess::Async)
+    );
+
+    let mut buffer = gst::Buffer::new();
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    let (res, state, _) = pipeline.state(gst::ClockTime::from_seconds(5));
+    assert_eq!(res, Ok(gst::StateChangeSuccess::Success));
+    assert_eq!(state, gst::State::Paused);
+
+    let last_sample = fakesink.property::<Option<gst::Sample>>("last-sample");
+    assert_eq!(
+        last_sample.unwrap().buffer().unwrap().pts(),
+        Some(gst::ClockTime::ZERO)
+    );
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn ring_queue_backend() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-ring-queue-backend");
+        appsrc.set_property_from_str("queue-backend", "ring");
+        appsrc.set_property("max-buffers", 2u32);
+    }
+
+    h.play();
+
+    // Pause the task so that the items pile up, as with the channel one more item fits
+    let appsrc = h.element().unwrap();
+    appsrc.set_state(gst::State::Paused).unwrap();
+
+    for offset in 0..3 {
+        let mut buffer = gst::Buffer::new();
+        buffer.get_mut().unwrap().set_offset(offset);
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    }
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    h.play();
+
+    for offset in 0..3 {
+        assert_eq!(h.pull().unwrap().offset(), offset);
+    }
+}
+
+#[test]
+fn compute_timestamp() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("emit-signals", true);
+        appsrc.set_property("context", "appsrc-compute-timestamp");
+
+        appsrc.connect("compute-timestamp", false, |args| {
+            let buffer = args[1].get::<gst::Buffer>().unwrap();
+            let running_time = args[2].get::<u64>().unwrap();
+            // Buffers with a size of 0 fall back to do-timestamp
+            if buffer.size() == 0 {
+                return Some(None::<gst::Structure>.to_value());
+            }
+
+            Some(
+                gst::Structure::builder("timestamps")
+                    .field(
+                        "pts",
+                        running_time.nseconds() + gst::ClockTime::from_mseconds(10),
+                    )
+                    .field("dts", running_time.nseconds())
+                    .build()
+                    .to_value(),
+            )
+        });
+    }
+
+    h.use_testclock();
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let base_time = appsrc.base_time().unwrap();
+    h.set_time(base_time + gst::ClockTime::from_mseconds(1000))
+        .unwrap();
+
+    let buffer = gst::Buffer::with_size(4).unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(1010)));
+    assert_eq!(buffer.dts(), Some(gst::ClockTime::from_mseconds(1000)));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(1000)));
+    assert_eq!(buffer.dts(), Some(gst::ClockTime::from_mseconds(1000)));
+}
+
+#[test]
+fn late_link_sticky_events() {
+    use std::sync::mpsc;
+
+    init();
+
+    let pipeline = gst::Pipeline::default();
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", "appsrc-late-link-sticky-events")
+        .property("caps", &caps)
+        .build()
+        .unwrap();
+    appsrc.set_property_from_str("not-linked-policy", "drop");
+
+    let sink = gst_app::AppSink::builder()
+        .sync(false)
+        .async_(false)
+        .build();
+
+    let (pushed_tx, pushed_rx) = mpsc::sync_channel(1);
+    appsrc
+        .static_pad("src")
+        .unwrap()
+        .add_probe(gst::PadProbeType::BUFFER, move |_, _| {
+            let _ = pushed_tx.try_send(());
+            gst::PadProbeReturn::Ok
+        })
+        .unwrap();
+
+    pipeline.add_many([&appsrc, sink.upcast_ref()]).unwrap();
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    // The first buffer is pushed while not linked
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    pushed_rx
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+
+    appsrc.link(&sink).unwrap();
+
+    // The sticky events are replayed before the next buffer
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let sample = sink
+        .try_pull_sample(gst::ClockTime::from_seconds(5))
+        .unwrap();
+    assert_eq!(sample.caps().unwrap(), caps.as_ref());
+    assert!(sample.segment().is_some());
+    assert!(sink
+        .static_pad("sink")
+        .unwrap()
+        .sticky_event::<gst::event::StreamStart>(0)
+        .is_some());
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn default_caps() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let default_caps = gst::Caps::builder("video/x-raw")
+        .field("format", gst::List::new(["I420", "NV12"]))
+        .build();
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "NV12")
+        .build();
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("default-caps", &default_caps);
+        appsrc.set_property("context", "appsrc-default-caps");
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let pad = appsrc.static_pad("src").unwrap();
+
+    // The default caps are advertised, but not sent
+    assert_eq!(pad.query_caps(None), default_caps);
+    let filter = gst::Caps::builder("video/x-raw")
+        .field("format", "I420")
+        .build();
+    assert_eq!(pad.query_caps(Some(&filter)), filter);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+    assert!(pad.current_caps().is_none());
+
+    // The configured caps take over
+    assert!(appsrc.emit_by_name::<bool>("push-buffer-with-caps", &[&caps, &gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+    assert_eq!(pad.query_caps(None), caps);
+}
+
+#[test]
+fn throttle() {
+    use gstthreadshare::appsrc::THROTTLE_EVENT_NAME;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-throttle");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let throttle_event = |throttled: bool| {
+        gst::event::CustomUpstream::new(
+            gst::Structure::builder(THROTTLE_EVENT_NAME)
+                .field("throttled", throttled)
+                .build(),
+        )
+    };
+
+    assert!(h.push_upstream_event(throttle_event(true)));
+    assert!(appsrc.property::<bool>("throttled"));
+
+    // Buffers are kept queued while throttled
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(h.try_pull().is_none());
+    assert!(appsrc.property::<u64>("oldest-buffer-age") > 0);
+
+    assert!(h.push_upstream_event(throttle_event(false)));
+    assert!(!appsrc.property::<bool>("throttled"));
+    let _ = h.pull().unwrap();
+}
+
+#[test]
+fn reject_when_paused() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-reject-when-paused");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_state(gst::State::Paused).unwrap();
+
+    // Buffers are queued by default
+    let mut buffer = gst::Buffer::new();
+    buffer.get_mut().unwrap().set_offset(0);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+
+    appsrc.set_property("reject-when-paused", true);
+    assert!(!appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+
+    h.play();
+
+    assert_eq!(h.pull().unwrap().offset(), 0);
+
+    // Only applies while paused
+    let mut buffer = gst::Buffer::new();
+    buffer.get_mut().unwrap().set_offset(1);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    assert_eq!(h.pull().unwrap().offset(), 1);
+}
+
+#[test]
+fn byte_seek() {
+    use std::sync::mpsc;
+
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let (offset_tx, offset_rx) = mpsc::sync_channel(1);
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-byte-seek");
+        appsrc.set_property_from_str("format", "bytes");
+        appsrc.set_property_from_str("stream-type", "random-access");
+
+        appsrc.connect("seek-data", false, move |args| {
+            let _ = offset_tx.try_send(args[1].get::<u64>().unwrap());
+            Some(true.to_value())
+        });
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let seek_with_flags = |flags: gst::SeekFlags, format_start: gst::GenericFormattedValue| {
+        gst::event::Seek::new(
+            1.0,
+            flags,
+            gst::SeekType::Set,
+            format_start,
+            gst::SeekType::None,
+            format_start,
+        )
+    };
+    let seek = |format_start| seek_with_flags(gst::SeekFlags::FLUSH, format_start);
+
+    // Only seeks in the configured format are handled
+    assert!(!h.push_upstream_event(seek(gst::ClockTime::from_seconds(1).into())));
+    // Only flushing seeks are handled
+    assert!(!h.push_upstream_event(seek_with_flags(gst::SeekFlags::empty(), 100.bytes().into())));
+
+    let seek_event = seek(100.bytes().into());
+    let seqnum = seek_event.seqnum();
+    assert!(h.push_upstream_event(seek_event));
+    assert_eq!(
+        offset_rx
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap(),
+        100
+    );
+
+    // The buffers pushed after the seek start in the new segment
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+    let segment = h
+        .sinkpad()
+        .unwrap()
+        .sticky_event::<gst::event::Segment>(0)
+        .unwrap();
+    let segment = segment
+        .segment()
+        .downcast_ref::<gst::format::Bytes>()
+        .unwrap();
+    assert_eq!(segment.start(), Some(100.bytes()));
+
+    // The flush and segment events carry the seqnum of the seek
+    let mut seqnums = vec![];
+    while let Some(event) = h.try_pull_event() {
+        if matches!(
+            event.type_(),
+            gst::EventType::FlushStart | gst::EventType::FlushStop | gst::EventType::Segment
+        ) {
+            seqnums.push((event.type_(), event.seqnum()));
+        }
+    }
+    assert!(seqnums.len() >= 3, "{seqnums:?}");
+    for (event_type, event_seqnum) in &seqnums[seqnums.len() - 3..] {
+        assert_eq!(*event_seqnum, seqnum, "{event_type:?}");
+    }
+
+    // Not seekable by default
+    appsrc.set_property_from_str("stream-type", "stream");
+    assert!(!h.push_upstream_event(seek(200.bytes().into())));
+}
+
+#[test]
+#[cfg(feature = "v1_20")]
+fn compress_queue() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-compress-queue");
+        appsrc.set_property("compress-queue", true);
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // Silence compresses well, the few bytes don't
+    let silence = vec![0u8; 4096];
+    let mut buffer = gst::Buffer::from_slice(silence.clone());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::from_mseconds(10));
+        buffer.set_flags(gst::BufferFlags::DISCONT);
+    }
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::from_slice([1u8, 2, 3])]));
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(
+        buffer.map_readable().unwrap().as_slice(),
+        silence.as_slice()
+    );
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(10)));
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+    assert!(gst::meta::CustomMeta::from_buffer(&buffer, "GstTsAppSrcCompressedMeta").is_err());
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.map_readable().unwrap().as_slice(), &[1, 2, 3]);
+}
+
+#[test]
+fn location() {
+    init();
+
+    let path = std::env::temp_dir().join(format!("ts-appsrc-location-{}", std::process::id()));
+    std::fs::write(&path, (0u8..10).collect::<Vec<_>>()).unwrap();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-location");
+        appsrc.set_property("location", path.to_str().unwrap());
+        appsrc.set_property("blocksize", 4u32);
+    }
+
+    h.play();
+
+    for (offset, data) in [(0, &[0u8, 1, 2, 3][..]), (4, &[4, 5, 6, 7]), (8, &[8, 9])] {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.offset(), offset);
+        assert_eq!(buffer.map_readable().unwrap().as_slice(), data);
+    }
+
+    // The end of the file is handled as an EOS
+    loop {
+        let event = h.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn location_queued() {
+    init();
+
+    let path =
+        std::env::temp_dir().join(format!("ts-appsrc-location-queued-{}", std::process::id()));
+    std::fs::write(&path, (0u8..10).collect::<Vec<_>>()).unwrap();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-location-queued");
+    appsrc.set_property("location", path.to_str().unwrap());
+    appsrc.set_property("blocksize", 1u32);
+    appsrc.set_property("do-timestamp", true);
+    // The reader waits for room instead of dropping chunks
+    appsrc.set_property("max-buffers", 1u32);
+
+    h.play();
+
+    for offset in 0..2 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.offset(), offset);
+        assert!(buffer.pts().is_some());
+    }
+
+    // Flushing drops the queued chunks but doesn't rewind the file
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+    let buffer = h.pull().unwrap();
+    assert!(buffer.offset() >= 2);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn drop_empty_buffers() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-drop-empty-buffers");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // Forwarded by default
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert_eq!(h.pull().unwrap().size(), 0);
+
+    appsrc.set_property("drop-empty-buffers", true);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let mut buffer = gst::Buffer::new();
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::SECOND);
+    assert_eq!(
+        appsrc.emit_by_name::<u64>("push-buffer-get-timestamp", &[&buffer]),
+        u64::MAX
+    );
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::with_size(4).unwrap()]));
+    assert_eq!(h.pull().unwrap().size(), 4);
+}
+
+#[test]
+fn segment_property() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    h.element()
+        .unwrap()
+        .set_property("context", "appsrc-segment-property");
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    // A default time segment before the first one is sent
+    let segment = appsrc.property::<gst::Segment>("segment");
+    assert_eq!(
+        segment,
+        gst::FormattedSegment::<gst::ClockTime>::new().upcast()
+    );
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(gst::ClockTime::from_mseconds(100));
+    assert!(appsrc.emit_by_name::<bool>("push-event", &[&gst::event::Segment::new(&segment)]));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    assert_eq!(appsrc.property::<gst::Segment>("segment"), segment.upcast());
+}
+
+#[test]
+fn stream_flags() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let stream_flags = gst::StreamFlags::SPARSE | gst::StreamFlags::SELECT;
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-stream-flags");
+        appsrc.set_property("stream-flags", stream_flags);
+
+        // Exclusive flags are ignored
+        appsrc.set_property(
+            "stream-flags",
+            gst::StreamFlags::SELECT | gst::StreamFlags::UNSELECT,
+        );
+        assert_eq!(
+            appsrc.property::<gst::StreamFlags>("stream-flags"),
+            stream_flags
+        );
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let _ = h.pull().unwrap();
+
+    let stream_start = h
+        .sinkpad()
+        .unwrap()
+        .sticky_event::<gst::event::StreamStart>(0)
+        .unwrap();
+    assert_eq!(stream_start.stream_flags(), stream_flags);
+}
+
+#[test]
+fn max_bitrate() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("context", "appsrc-max-bitrate");
+        // 10 kB/s, i.e. bursts of 1 kB
+        appsrc.set_property("max-bitrate", 80_000u64);
+    }
+
+    h.play();
+
+    let appsrc = h.element().unwrap();
+
+    let start = std::time::Instant::now();
+    for _ in 0..3 {
+        let buffer = gst::Buffer::with_size(1000).unwrap();
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    }
+    for _ in 0..3 {
+        let _ = h.pull().unwrap();
+    }
+
+    // The first buffer fits in the burst, each other one takes 100ms
+    assert!(start.elapsed() >= std::time::Duration::from_millis(180));
+}
+
+#[test]
+fn push_buffer_get_timestamp() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    {
+        let appsrc = h.element().unwrap();
+        appsrc.set_property("do-timestamp", true);
+        appsrc.set_property("context", "appsrc-push-buffer-get-timestamp");
+    }
+
+    h.use_testclock();
+    h.play();
+
+    let appsrc = h.element().unwrap();
+    let base_time = appsrc.base_time().unwrap();
+    h.set_time(base_time + gst::ClockTime::from_mseconds(1000))
+        .unwrap();
+
+    let timestamp = appsrc.emit_by_name::<u64>("push-buffer-get-timestamp", &[&gst::Buffer::new()]);
+    assert_eq!(timestamp, gst::ClockTime::from_mseconds(1000).nseconds());
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(1000)));
+
+    // Rejected buffers
+    appsrc.set_state(gst::State::Null).unwrap();
+    let timestamp = appsrc.emit_by_name::<u64>("push-buffer-get-timestamp", &[&gst::Buffer::new()]);
+    assert_eq!(timestamp, u64::MAX);
+}
+
+#[test]
+fn strict_zero_copy() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-strict-zero-copy");
+    appsrc.set_property("do-timestamp", false);
+    appsrc.set_property("strict-zero-copy", true);
+
+    h.play();
+
+    // Still referenced here, so the discont flag can't be set without a copy
+    let buffer = gst::Buffer::new();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let pulled = h.pull().unwrap();
+    assert_eq!(pulled.as_ptr(), buffer.as_ptr());
+    assert!(!pulled.flags().contains(gst::BufferFlags::DISCONT));
+
+    assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+    assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+
+    appsrc.set_property("strict-zero-copy", false);
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let pulled = h.pull().unwrap();
+    assert_ne!(pulled.as_ptr(), buffer.as_ptr());
+    assert!(pulled.flags().contains(gst::BufferFlags::DISCONT));
+    assert!(!buffer.flags().contains(gst::BufferFlags::DISCONT));
+}
+
+#[test]
+fn push_event_at() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-push-event-at");
+    appsrc.set_property("do-timestamp", false);
+
+    h.play();
+
+    let marker = |name: &str| gst::event::CustomDownstream::new(gst::Structure::new_empty(name));
+    let push_buffer = |pts: u64| {
+        let mut buffer = gst::Buffer::new();
+        buffer
+            .get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(pts));
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    };
+    let pull_marker = |h: &mut gst_check::Harness| {
+        while let Some(event) = h.try_pull_event() {
+            if let gst::EventView::CustomDownstream(ev) = event.view() {
+                return Some(ev.structure().unwrap().name().to_string());
+            }
+        }
+        None
+    };
+
+    assert!(appsrc.emit_by_name::<bool>(
+        "push-event-at",
+        &[
+            &marker("scheduled"),
+            &gst::ClockTime::from_mseconds(150).nseconds()
+        ],
+    ));
+    push_buffer(0);
+    push_buffer(100);
+    assert_eq!(h.pull().unwrap().pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(
+        h.pull().unwrap().pts(),
+        Some(gst::ClockTime::from_mseconds(100))
+    );
+    assert_eq!(pull_marker(&mut h), None);
+
+    // Pushed before the first buffer starting after the scheduled running time
+    push_buffer(200);
+    assert_eq!(
+        h.pull().unwrap().pts(),
+        Some(gst::ClockTime::from_mseconds(200))
+    );
+    assert_eq!(pull_marker(&mut h).as_deref(), Some("scheduled"));
+
+    // Already passed: pushed immediately
+    assert!(appsrc.emit_by_name::<bool>(
+        "push-event-at",
+        &[
+            &marker("late"),
+            &gst::ClockTime::from_mseconds(50).nseconds()
+        ],
+    ));
+    let event = h.pull_event().unwrap();
+    assert_eq!(
+        event.structure().unwrap().name().as_str(),
+        "late",
+        "{event:?}"
+    );
+
+    // Not a serialized downstream event
+    let flush_start = gst::event::FlushStart::new();
+    assert!(!appsrc.emit_by_name::<bool>("push-event-at", &[&flush_start, &0u64]));
+
+    // Still held at EOS: pushed before the EOS
+    assert!(appsrc.emit_by_name::<bool>(
+        "push-event-at",
+        &[
+            &marker("pending"),
+            &gst::ClockTime::from_seconds(10).nseconds()
+        ],
+    ));
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    let mut events = vec![];
+    loop {
+        let event = h.pull_event().unwrap();
+        let is_eos = event.type_() == gst::EventType::Eos;
+        events.push(event);
+        if is_eos {
+            break;
+        }
+    }
+    assert_eq!(
+        events[events.len() - 2]
+            .structure()
+            .unwrap()
+            .name()
+            .as_str(),
+        "pending"
+    );
+}
+
+#[test]
+fn buffering() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-buffering");
+    appsrc.set_property("max-buffers", 4u32);
+    appsrc.set_property("use-buffering", true);
+    appsrc.set_property("low-watermark", 0.25f64);
+    appsrc.set_property("high-watermark", 0.75f64);
+    assert!(appsrc.property::<bool>("use-buffering"));
+    assert_eq!(appsrc.property::<f64>("low-watermark"), 0.25);
+    assert_eq!(appsrc.property::<f64>("high-watermark"), 0.75);
+
+    // Unordered watermarks are ignored
+    appsrc.set_property("low-watermark", 0.8f64);
+    assert_eq!(appsrc.property::<f64>("low-watermark"), 0.25);
+    appsrc.set_property("high-watermark", 0.2f64);
+    assert_eq!(appsrc.property::<f64>("high-watermark"), 0.75);
+
+    let bus = gst::Bus::new();
+    appsrc.set_bus(Some(&bus));
+    let buffering_percents = || {
+        std::iter::from_fn(|| bus.pop_filtered(&[gst::MessageType::Buffering]))
+            .map(|msg| match msg.view() {
+                gst::MessageView::Buffering(msg) => msg.percent(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    h.play();
+
+    // Pause the task so that the buffers pile up
+    appsrc.set_state(gst::State::Paused).unwrap();
+    assert!(appsrc.property::<bool>("buffering"));
+
+    for _ in 0..2 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    assert!(appsrc.property::<bool>("buffering"));
+    assert_eq!(buffering_percents(), [25, 50]);
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    assert!(!appsrc.property::<bool>("buffering"));
+    assert_eq!(buffering_percents(), [100]);
+
+    // Draining: buffering again once below the low watermark
+    appsrc.set_state(gst::State::Playing).unwrap();
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+    assert!(!appsrc.property::<bool>("buffering"));
+    let _ = h.pull().unwrap();
+    assert!(appsrc.property::<bool>("buffering"));
+    assert_eq!(buffering_percents(), [0]);
+
+    // Filling again
+    appsrc.set_state(gst::State::Paused).unwrap();
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+    assert!(!appsrc.property::<bool>("buffering"));
+    assert_eq!(buffering_percents(), [25, 50, 100]);
+
+    // No more data to wait for
+    appsrc.set_state(gst::State::Playing).unwrap();
+    for _ in 0..3 {
+        let _ = h.pull().unwrap();
+    }
+    assert_eq!(buffering_percents(), [0]);
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    assert!(!appsrc.property::<bool>("buffering"));
+    assert_eq!(buffering_percents(), [100]);
+}
+
+#[test]
+fn switch_format() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let caps = gst::Caps::builder("foo/bar").build();
+    let new_caps = gst::Caps::builder("foo/baz").build();
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-switch-format");
+    appsrc.set_property("caps", &caps);
+
+    h.play();
+
+    let make_buffer = |offset: u64| {
+        let mut buffer = gst::Buffer::new();
+        buffer.get_mut().unwrap().set_offset(offset);
+        buffer
+    };
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(0)]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.offset(), 0);
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::StreamStart);
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::Segment);
+
+    // Queued in the previous format, dropped by the switch
+    appsrc.set_state(gst::State::Paused).unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(1)]));
+
+    assert!(appsrc.emit_by_name::<bool>("switch-format", &[&new_caps]));
+    assert_eq!(
+        appsrc.property::<Option<gst::Caps>>("caps"),
+        Some(new_caps.clone())
+    );
+
+    appsrc.set_state(gst::State::Playing).unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(2)]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.offset(), 2);
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+
+    // Flush, new caps and fresh segment, no new stream-start
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::FlushStart);
+    assert_eq!(h.pull_event().unwrap().type_(), gst::EventType::FlushStop);
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Caps(ev) => assert_eq!(new_caps.as_ref(), ev.caps()),
+        _ => panic!("Unexpected event {event:?}"),
+    }
+    let event = h.pull_event().unwrap();
+    match event.view() {
+        gst::EventView::Segment(ev) => {
+            assert_eq!(
+                ev.segment(),
+                gst::FormattedSegment::<gst::ClockTime>::new().upcast_ref()
+            )
+        }
+        _ => panic!("Unexpected event {event:?}"),
+    }
+    assert!(h.try_pull_event().is_none());
+
+    // Caps locked to the current ones
+    appsrc.set_property("lock-caps", true);
+    assert!(!appsrc.emit_by_name::<bool>("switch-format", &[&caps]));
+    assert_eq!(appsrc.property::<Option<gst::Caps>>("caps"), Some(new_caps));
+}
+
+#[test]
+fn min_fill() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-min-fill");
+    appsrc.set_property("max-buffers", 4u32);
+    appsrc.set_property("min-fill", 3u32);
+
+    h.play();
+
+    let push_buffers = |n: usize| {
+        for _ in 0..n {
+            assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    let flush = |h: &mut gst_check::Harness| {
+        assert!(h.push_upstream_event(gst::event::FlushStart::new()));
+        assert!(h.push_upstream_event(gst::event::FlushStop::new(true)));
+    };
+
+    push_buffers(2);
+    assert!(h.try_pull().is_none());
+    push_buffers(1);
+    for _ in 0..3 {
+        let _ = h.pull().unwrap();
+    }
+
+    // Filled: pushing normally
+    push_buffers(1);
+    let _ = h.pull().unwrap();
+
+    // Re-applies after a flush, the greater of min-fill and min-fill-percent.
+    // The percentage is relative to the queue capacity, max-buffers changes
+    // only apply when going to READY.
+    flush(&mut h);
+    appsrc.set_property("max-buffers", 100u32);
+    appsrc.set_property("min-fill", 0u32);
+    appsrc.set_property("min-fill-percent", 50u32);
+    push_buffers(1);
+    assert!(h.try_pull().is_none());
+    push_buffers(1);
+    for _ in 0..2 {
+        let _ = h.pull().unwrap();
+    }
+
+    // No more buffers to wait for after an EOS
+    flush(&mut h);
+    push_buffers(1);
+    assert!(h.try_pull().is_none());
+    assert!(appsrc.emit_by_name::<bool>("end-of-stream", &[]));
+    let _ = h.pull().unwrap();
+    loop {
+        if h.pull_event().unwrap().type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+}
+
+#[test]
+fn upstream_event() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-upstream-event");
+
+    h.play();
+
+    let custom_event =
+        || gst::event::CustomUpstream::new(gst::Structure::new_empty("application/x-custom"));
+
+    // No handler
+    assert!(!h.push_upstream_event(custom_event()));
+
+    let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    appsrc.connect("upstream-event", false, {
+        let received = received.clone();
+        move |args| {
+            let event = args[1].get::<gst::Event>().unwrap();
+            let handled = event.type_() == gst::EventType::CustomUpstream;
+            received.lock().unwrap().push(event.type_());
+            Some(handled.to_value())
+        }
+    });
+
+    // Gated behind emit-signals
+    appsrc.set_property("emit-signals", false);
+    assert!(!h.push_upstream_event(custom_event()));
+    assert!(received.lock().unwrap().is_empty());
+
+    appsrc.set_property("emit-signals", true);
+    assert!(h.push_upstream_event(custom_event()));
+    assert!(
+        !h.push_upstream_event(gst::event::Navigation::new(gst::Structure::new_empty(
+            "application/x-gst-navigation"
+        )))
+    );
+    // Handled by the element
+    assert!(h.push_upstream_event(gst::event::Latency::new(gst::ClockTime::ZERO)));
+    assert_eq!(
+        *received.lock().unwrap(),
+        [gst::EventType::CustomUpstream, gst::EventType::Navigation]
+    );
+}
+
+#[test]
+fn max_buffer_age() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-max-buffer-age");
+    appsrc.set_property(
+        "max-buffer-age",
+        gst::ClockTime::from_mseconds(50).nseconds(),
+    );
+
+    h.play();
+
+    let make_buffer = |offset: u64| {
+        let mut buffer = gst::Buffer::new();
+        buffer.get_mut().unwrap().set_offset(offset);
+        buffer
+    };
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(0)]));
+    let _ = h.pull().unwrap();
+
+    // Pause the task so that the first buffer goes stale in the queue
+    appsrc.set_state(gst::State::Paused).unwrap();
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(1)]));
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&make_buffer(2)]));
+    appsrc.set_state(gst::State::Playing).unwrap();
+
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.offset(), 2);
+    assert!(buffer.flags().contains(gst::BufferFlags::DISCONT));
+
+    let stats = appsrc.emit_by_name::<gst::Structure>("get-stats", &[]);
+    assert_eq!(stats.get::<u64>("age-drops").unwrap(), 1);
+}
+
+#[test]
+fn do_timestamp_preserves_flags() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-do-timestamp-preserves-flags");
+    appsrc.set_property("do-timestamp", true);
+    // Only the application sets DISCONT
+    appsrc.set_property("auto-discont", false);
+
+    h.use_testclock();
+    h.play();
+
+    let base_time = appsrc.base_time().unwrap();
+    h.set_time(base_time + gst::ClockTime::from_mseconds(100))
+        .unwrap();
+
+    let flags = gst::BufferFlags::DISCONT | gst::BufferFlags::HEADER | gst::BufferFlags::DELTA_UNIT;
+    let mut buffer = gst::Buffer::new();
+    buffer.get_mut().unwrap().set_flags(flags);
+
+    // Keep a reference so that timestamping copies the buffer
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    let pulled = h.pull().unwrap();
+    assert_ne!(pulled.as_ptr(), buffer.as_ptr());
+    assert_eq!(pulled.pts(), Some(gst::ClockTime::from_mseconds(100)));
+    assert!(pulled.flags().contains(flags), "{:?}", pulled.flags());
+    assert_eq!(buffer.pts(), None);
+}
+
+#[test]
+fn sparse() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-sparse");
+    appsrc.set_property("sparse", true);
+    // Ignored, the buffers keep their timestamps
+    appsrc.set_property("do-timestamp", true);
+    appsrc.set_property(
+        "caps",
+        gst::Caps::builder("text/x-raw")
+            .field("framerate", gst::Fraction::new(10, 1))
+            .build(),
+    );
+    assert_eq!(appsrc.property::<u64>("latency"), 0);
+
+    h.play();
+
+    // Irregular timing: two gaps, then back to back buffers
+    for (pts, duration) in [(1000, 500), (3000, 1000), (4000, 500)] {
+        let mut buffer = gst::Buffer::new();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(pts));
+            buffer.set_duration(gst::ClockTime::from_mseconds(duration));
+        }
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]));
+    }
+    for pts in [1000, 3000, 4000] {
+        assert_eq!(
+            h.pull().unwrap().pts(),
+            Some(gst::ClockTime::from_mseconds(pts))
+        );
+    }
+
+    let mut gaps = Vec::new();
+    while let Some(event) = h.try_pull_event() {
+        match event.view() {
+            gst::EventView::StreamStart(ev) => {
+                assert!(ev.stream_flags().contains(gst::StreamFlags::SPARSE))
+            }
+            gst::EventView::Gap(ev) => {
+                let (start, duration) = ev.get();
+                gaps.push((start.mseconds(), duration.unwrap().mseconds()));
+            }
+            _ => (),
+        }
+    }
+    assert_eq!(gaps, [(0, 1000), (1500, 1500)]);
+}
+
+#[test]
+fn state_toggle_stress() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", "appsrc-state-toggle-stress")
+        .property("max-buffers", 100u32)
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", false)
+        .property("async", false)
+        .build()
+        .unwrap();
+    pipeline.add_many([&appsrc, &sink]).unwrap();
+    appsrc.link(&sink).unwrap();
+
+    // Keep pushing while the element is torn down and set up again
+    let stop = Arc::new(AtomicBool::new(false));
+    let pusher = std::thread::spawn({
+        let appsrc = appsrc.clone();
+        let stop = stop.clone();
+        move || {
+            let mut pushed = 0u64;
+            while !stop.load(Ordering::SeqCst) {
+                let buffer = gst::Buffer::with_size(64).unwrap();
+                if appsrc.emit_by_name::<bool>("push-buffer", &[&buffer]) {
+                    pushed += 1;
+                }
+            }
+            pushed
+        }
+    });
+
+    let src_pad = appsrc.static_pad("src").unwrap();
+    for idx in 0..50 {
+        pipeline.set_state(gst::State::Playing).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        if idx % 2 == 0 {
+            src_pad.send_event(gst::event::FlushStart::new());
+            src_pad.send_event(gst::event::FlushStop::new(true));
+        }
+        pipeline.set_state(gst::State::Null).unwrap();
+    }
+
+    stop.store(true, Ordering::SeqCst);
+    assert!(pusher.join().unwrap() > 0);
+}
+
+#[test]
+fn passive() {
+    init();
+
+    // No downstream at all
+    let appsrc = gst::ElementFactory::make("ts-appsrc")
+        .property("context", "appsrc-passive")
+        .property("passive", true)
+        .build()
+        .unwrap();
+
+    appsrc.set_state(gst::State::Playing).unwrap();
+
+    for _ in 0..3 {
+        assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    }
+
+    let start = std::time::Instant::now();
+    while appsrc.property::<u64>("buffer-count") < 3 {
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(appsrc.current_state(), gst::State::Playing);
+
+    appsrc.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn do_timestamp_before_base_time() {
+    init();
+
+    let mut h = gst_check::Harness::new("ts-appsrc");
+
+    let appsrc = h.element().unwrap();
+    appsrc.set_property("context", "appsrc-do-timestamp-before-base-time");
+    appsrc.set_property("do-timestamp", true);
+
+    h.use_testclock();
+    h.play();
+
+    // The clock is behind the base time
+    let now = h.testclock().unwrap().time().unwrap();
+    appsrc.set_base_time(now + gst::ClockTime::from_seconds(10));
+
+    assert!(appsrc.emit_by_name::<bool>("push-buffer", &[&gst::Buffer::new()]));
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(buffer.dts(), Some(gst::ClockTime::ZERO));
+}